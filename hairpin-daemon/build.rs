@@ -1,4 +1,7 @@
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    tonic_build::compile_protos("../proto/hairpin.proto")?;
+    // Item values are sent as slices of one buffer rather than copies of it.
+    tonic_build::configure()
+        .bytes([".hairpin.ReadItemResponse.chunk"])
+        .compile_protos(&["../proto/hairpin.proto"], &["../proto"])?;
     Ok(())
 }
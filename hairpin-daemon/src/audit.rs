@@ -0,0 +1,531 @@
+use std::{
+    fmt::Debug,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use hmac::{Hmac, Mac};
+use libmount::fs::FileSystemInfo;
+use rand_core::{OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::Sha256;
+use tokio::{io::AsyncWriteExt, sync::Mutex};
+use zeroize::Zeroizing;
+
+use crate::{
+    Error,
+    policy::{Action, Caller},
+};
+
+/// Something that happened to a source or one of its items. Never carries item values.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum AuditEvent {
+    SourceCreated {
+        caller: Caller,
+        source: u64,
+        location: String,
+    },
+    SourceDeleted {
+        caller: Caller,
+        source: u64,
+        location: String,
+    },
+    SourceMounted {
+        source: u64,
+        location: String,
+        filesystem: FileSystemInfo,
+    },
+    SourceUnmounted {
+        source: u64,
+        location: String,
+    },
+    SourceUnlocked {
+        caller: Caller,
+        source: u64,
+    },
+    SourceReloaded {
+        source: u64,
+        added: Vec<String>,
+        removed: Vec<String>,
+        changed: Vec<String>,
+    },
+    TemplateDelivered {
+        source: u64,
+        target: String,
+        items: Vec<String>,
+    },
+    ItemRead {
+        caller: Caller,
+        source: u64,
+        item: String,
+        name: String,
+    },
+    ItemDelivered {
+        source: u64,
+        item: String,
+        name: String,
+    },
+    Denied {
+        caller: Caller,
+        action: Action,
+        manifest: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        item: Option<String>,
+        reason: String,
+    },
+}
+#[derive(Debug, Serialize)]
+struct Entry<'a> {
+    /// Milliseconds since the unix epoch.
+    time: u64,
+    #[serde(flatten)]
+    event: &'a AuditEvent,
+    /// Position in the chain, counting from 1.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seq: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    prev: Option<&'a str>,
+}
+/// Key chaining the audit log, kept apart from it so whoever can rewrite the log can't forge
+/// entries.
+#[derive(Clone)]
+pub struct AuditKey(Hmac<Sha256>);
+impl Debug for AuditKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("AuditKey")
+    }
+}
+impl AuditKey {
+    const LEN: usize = 32;
+    /// Reads a hex encoded key.
+    pub fn read(path: &Path) -> Result<Self, Error> {
+        let key = Zeroizing::new(std::fs::read_to_string(path)?);
+        Self::decode(path, &key)
+    }
+    /// Reads the key at `path`, generating one readable only by the daemon if there's none.
+    pub async fn load_or_create(path: &Path) -> Result<Self, Error> {
+        match tokio::fs::read_to_string(path).await {
+            Ok(key) => return Self::decode(path, &Zeroizing::new(key)),
+            Err(err) if err.kind() != std::io::ErrorKind::NotFound => return Err(err.into()),
+            Err(_) => {}
+        }
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        let mut key = Zeroizing::new([0u8; Self::LEN]);
+        OsRng.fill_bytes(key.as_mut());
+        let encoded = Zeroizing::new(hex::encode(key.as_ref()));
+        let mut file = tokio::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .mode(0o600)
+            .open(path)
+            .await?;
+        file.write_all(encoded.as_bytes()).await?;
+        file.sync_all().await?;
+        Self::decode(path, &encoded)
+    }
+    fn decode(path: &Path, key: &str) -> Result<Self, Error> {
+        let key = Zeroizing::new(
+            hex::decode(key.trim()).map_err(|_| Error::InvalidAuditKey(path.to_path_buf()))?,
+        );
+        if key.len() != Self::LEN {
+            return Err(Error::InvalidAuditKey(path.to_path_buf()));
+        }
+        Hmac::new_from_slice(&key)
+            .map(Self)
+            .map_err(|_| Error::InvalidAuditKey(path.to_path_buf()))
+    }
+    fn sign(&self, data: &[u8]) -> String {
+        let mut mac = self.0.clone();
+        mac.update(data);
+        hex::encode(mac.finalize().into_bytes())
+    }
+    fn verify(&self, data: &[u8], signature: &str) -> bool {
+        let mut mac = self.0.clone();
+        mac.update(data);
+        hex::decode(signature).is_ok_and(|signature| mac.verify_slice(&signature).is_ok())
+    }
+}
+/// Where the chain starts and ends, written next to the log after every entry so truncating the
+/// log or dropping its oldest files is detectable.
+#[derive(Debug, Serialize, Deserialize)]
+struct Anchor {
+    first: u64,
+    last: u64,
+    hash: String,
+    mac: String,
+}
+impl Anchor {
+    fn new(key: &AuditKey, first: u64, last: u64, hash: String) -> Self {
+        let mac = key.sign(Self::signed(first, last, &hash).as_bytes());
+        Self {
+            first,
+            last,
+            hash,
+            mac,
+        }
+    }
+    fn signed(first: u64, last: u64, hash: &str) -> String {
+        format!("{first}:{last}:{hash}")
+    }
+    /// Reads the anchor of a log, failing if it wasn't written with `key`.
+    fn read(log: &Path, key: &AuditKey) -> Result<Option<Self>, Error> {
+        let path = Self::path(log);
+        let anchor = match std::fs::read_to_string(&path) {
+            Ok(anchor) => anchor,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+        let broken = |reason| Error::BrokenAuditChain(path.display().to_string(), 1, reason);
+        let anchor = serde_json::from_str::<Self>(&anchor).map_err(|_| broken("not an anchor"))?;
+        if !key.verify(
+            Self::signed(anchor.first, anchor.last, &anchor.hash).as_bytes(),
+            &anchor.mac,
+        ) {
+            return Err(broken("anchor was modified"));
+        }
+        Ok(Some(anchor))
+    }
+    async fn write(&self, log: &Path) -> Result<(), Error> {
+        let path = Self::path(log);
+        let mut temporary = path.as_os_str().to_owned();
+        temporary.push(".tmp");
+        tokio::fs::write(&temporary, serde_json::to_string(self)?).await?;
+        tokio::fs::rename(&temporary, &path).await?;
+        Ok(())
+    }
+    fn path(log: &Path) -> PathBuf {
+        let mut path = log.as_os_str().to_owned();
+        path.push(".head");
+        path.into()
+    }
+}
+#[derive(Debug)]
+struct Writer {
+    file: tokio::fs::File,
+    size: u64,
+    /// Sequence number of the oldest entry still kept, once there is one.
+    first: Option<u64>,
+    seq: u64,
+    last: Option<String>,
+}
+/// JSON lines log of audit events, rotated once it grows past a size. When chained, every entry
+/// carries its position and the HMAC of the preceding one, and an anchor next to the log records
+/// both ends of the chain, so removed or edited entries are detectable.
+#[derive(Debug)]
+pub struct AuditLog {
+    path: PathBuf,
+    max_size: Option<u64>,
+    keep: usize,
+    key: Option<AuditKey>,
+    fail_closed: bool,
+    writer: Mutex<Writer>,
+}
+impl AuditLog {
+    /// Appends to the log at `path`, continuing the chain of its anchor when given a key.
+    pub async fn open(
+        path: impl AsRef<Path>,
+        max_size: Option<u64>,
+        keep: usize,
+        key: Option<AuditKey>,
+    ) -> Result<Self, Error> {
+        let path = path.as_ref().to_path_buf();
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        let file = append(&path).await?;
+        let size = file.metadata().await?.len();
+        let anchor = match &key {
+            Some(key) => Anchor::read(&path, key)?,
+            None => None,
+        };
+        let writer = match anchor {
+            Some(anchor) => Writer {
+                file,
+                size,
+                first: Some(anchor.first),
+                seq: anchor.last,
+                last: Some(anchor.hash),
+            },
+            None => Writer {
+                file,
+                size,
+                first: None,
+                seq: 0,
+                last: None,
+            },
+        };
+        Ok(Self {
+            path,
+            max_size,
+            keep,
+            key,
+            fail_closed: false,
+            writer: Mutex::new(writer),
+        })
+    }
+    /// Fails accesses to item values that can't be recorded rather than allowing them.
+    pub fn with_fail_closed(mut self, fail_closed: bool) -> Self {
+        self.fail_closed = fail_closed;
+        self
+    }
+    pub fn fail_closed(&self) -> bool {
+        self.fail_closed
+    }
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+    pub async fn record(&self, event: &AuditEvent) -> Result<(), Error> {
+        let mut writer = self.writer.lock().await;
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|time| time.as_millis() as u64)
+            .unwrap_or_default();
+        let seq = self.key.as_ref().map(|_| writer.seq + 1);
+        let mut entry = serde_json::to_value(Entry {
+            time,
+            event,
+            seq,
+            prev: writer.last.as_deref(),
+        })?;
+        let mut hash = None;
+        if let Some(key) = &self.key
+            && let Value::Object(fields) = &mut entry
+        {
+            let mac = key.sign(serde_json::to_string(fields)?.as_bytes());
+            fields.insert("hash".to_string(), Value::String(mac.clone()));
+            hash = Some(mac);
+        }
+        let line = serde_json::to_string(&entry)? + "\n";
+        if self
+            .max_size
+            .is_some_and(|max_size| writer.size > 0 && writer.size + line.len() as u64 > max_size)
+        {
+            self.rotate(&mut writer).await?;
+        }
+        writer.file.write_all(line.as_bytes()).await?;
+        writer.file.flush().await?;
+        writer.size += line.len() as u64;
+        if let (Some(key), Some(seq), Some(hash)) = (&self.key, seq, hash) {
+            let first = *writer.first.get_or_insert(seq);
+            writer.seq = seq;
+            writer.last = Some(hash.clone());
+            Anchor::new(key, first, seq, hash).write(&self.path).await?;
+        }
+        Ok(())
+    }
+    /// Shifts `log.1` to `log.2` and so on, dropping the oldest, and starts a new log.
+    async fn rotate(&self, writer: &mut Writer) -> Result<(), Error> {
+        for index in (1..self.keep).rev() {
+            rename(&rotated(&self.path, index), &rotated(&self.path, index + 1)).await?;
+        }
+        match self.keep {
+            0 => tokio::fs::remove_file(&self.path).await?,
+            _ => rename(&self.path, &rotated(&self.path, 1)).await?,
+        }
+        writer.file = append(&self.path).await?;
+        writer.size = 0;
+        if self.key.is_some() {
+            writer.first = None;
+            for index in (1..=self.keep).rev() {
+                if let Some(seq) = first_seq(&rotated(&self.path, index)).await? {
+                    writer.first = Some(seq);
+                    break;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+/// Checks the chain of a log and its rotated files, oldest first, against the anchor written
+/// with them, returning the entry count.
+pub fn verify(path: &Path, key: &AuditKey) -> Result<usize, Error> {
+    let anchor = Anchor::read(path, key)?.ok_or_else(|| {
+        Error::BrokenAuditChain(
+            Anchor::path(path).display().to_string(),
+            1,
+            "anchor is missing",
+        )
+    })?;
+    let mut files = (1..)
+        .map(|index| rotated(path, index))
+        .take_while(|file| file.exists())
+        .collect::<Vec<_>>();
+    files.reverse();
+    files.push(path.to_path_buf());
+    let mut last: Option<String> = None;
+    let mut seq = anchor.first;
+    let mut count = 0;
+    let mut end = 0;
+    for file in &files {
+        let broken = |line: usize, reason| {
+            Error::BrokenAuditChain(file.display().to_string(), line + 1, reason)
+        };
+        let log = std::fs::read_to_string(file)?;
+        for (index, line) in log.lines().enumerate() {
+            end = index + 1;
+            if line.is_empty() {
+                continue;
+            }
+            let Ok(Value::Object(mut fields)) = serde_json::from_str::<Value>(line) else {
+                return Err(broken(index, "not a JSON object"));
+            };
+            let Some(Value::String(hash)) = fields.remove("hash") else {
+                return Err(broken(index, "entry isn't chained"));
+            };
+            if !key.verify(serde_json::to_string(&fields)?.as_bytes(), &hash) {
+                return Err(broken(index, "entry was modified"));
+            }
+            if fields.get("seq").and_then(Value::as_u64) != Some(seq) {
+                return Err(broken(
+                    index,
+                    match count {
+                        0 => "oldest entries are missing",
+                        _ => "preceding entry is missing",
+                    },
+                ));
+            }
+            let prev = fields.get("prev").and_then(Value::as_str);
+            if last.is_some() && prev != last.as_deref() {
+                return Err(broken(index, "preceding entry is missing"));
+            }
+            last = Some(hash);
+            seq += 1;
+            count += 1;
+        }
+    }
+    if seq != anchor.last + 1 || last.as_deref() != Some(anchor.hash.as_str()) {
+        return Err(Error::BrokenAuditChain(
+            path.display().to_string(),
+            end + 1,
+            "latest entries are missing",
+        ));
+    }
+    Ok(count)
+}
+fn rotated(path: &Path, index: usize) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(format!(".{index}"));
+    path.into()
+}
+async fn append(path: &Path) -> Result<tokio::fs::File, Error> {
+    Ok(tokio::fs::OpenOptions::new()
+        .append(true)
+        .create(true)
+        .mode(0o600)
+        .open(path)
+        .await?)
+}
+async fn rename(from: &Path, to: &Path) -> Result<(), Error> {
+    match tokio::fs::rename(from, to).await {
+        Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(err.into()),
+        _ => Ok(()),
+    }
+}
+/// Sequence number of the first entry in a log file, if it exists and is chained.
+async fn first_seq(path: &Path) -> Result<Option<u64>, Error> {
+    let log = match tokio::fs::read_to_string(path).await {
+        Ok(log) => log,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err.into()),
+    };
+    Ok(log
+        .lines()
+        .find(|line| !line.is_empty())
+        .and_then(|line| serde_json::from_str::<Value>(line).ok())
+        .and_then(|entry| entry.get("seq")?.as_u64()))
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("hairpin-audit-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+    /// Writes a chained log, rotated every few entries when `rotate` is set.
+    async fn log(name: &str, entries: u64, rotate: bool) -> (PathBuf, AuditKey) {
+        let dir = dir(name);
+        let key = AuditKey::load_or_create(&dir.join("audit.key"))
+            .await
+            .unwrap();
+        let path = dir.join("audit.log");
+        let log = AuditLog::open(&path, rotate.then_some(512), 2, Some(key.clone()))
+            .await
+            .unwrap();
+        for source in 0..entries {
+            log.record(&AuditEvent::SourceUnmounted {
+                source,
+                location: "file:///srv/app".to_string(),
+            })
+            .await
+            .unwrap();
+        }
+        (path, key)
+    }
+    fn reason(result: Result<usize, Error>) -> &'static str {
+        match result {
+            Err(Error::BrokenAuditChain(_, _, reason)) => reason,
+            result => panic!("expected a broken chain, got {result:?}"),
+        }
+    }
+    #[tokio::test]
+    async fn verifies_rotated_logs() {
+        let (path, key) = log("rotated", 20, true).await;
+        assert!(rotated(&path, 2).exists());
+        let kept = verify(&path, &key).unwrap();
+        assert!(kept > 0 && kept < 20);
+        let log = AuditLog::open(&path, Some(512), 2, Some(key.clone()))
+            .await
+            .unwrap();
+        log.record(&AuditEvent::SourceUnmounted {
+            source: 20,
+            location: "file:///srv/app".to_string(),
+        })
+        .await
+        .unwrap();
+        assert!(verify(&path, &key).is_ok());
+    }
+    #[tokio::test]
+    async fn detects_modified_entries() {
+        let (path, key) = log("modified", 3, false).await;
+        let log = std::fs::read_to_string(&path).unwrap();
+        std::fs::write(&path, log.replacen("\"source\":1", "\"source\":7", 1)).unwrap();
+        assert_eq!(reason(verify(&path, &key)), "entry was modified");
+    }
+    #[tokio::test]
+    async fn detects_removed_entries() {
+        let (path, key) = log("removed", 3, false).await;
+        let log = std::fs::read_to_string(&path).unwrap();
+        let lines = log.lines().collect::<Vec<_>>();
+        std::fs::write(&path, format!("{}\n{}\n", lines[0], lines[2])).unwrap();
+        assert_eq!(reason(verify(&path, &key)), "preceding entry is missing");
+    }
+    #[tokio::test]
+    async fn detects_truncation() {
+        let (path, key) = log("truncated", 3, false).await;
+        let log = std::fs::read_to_string(&path).unwrap();
+        let (kept, _) = log.trim_end().rsplit_once('\n').unwrap();
+        std::fs::write(&path, format!("{kept}\n")).unwrap();
+        assert_eq!(reason(verify(&path, &key)), "latest entries are missing");
+    }
+    #[tokio::test]
+    async fn detects_dropped_files() {
+        let (path, key) = log("dropped", 20, true).await;
+        std::fs::remove_file(rotated(&path, 2)).unwrap();
+        assert_eq!(reason(verify(&path, &key)), "oldest entries are missing");
+    }
+    #[tokio::test]
+    async fn rejects_other_keys() {
+        let (path, _) = log("key", 3, false).await;
+        let other = AuditKey::load_or_create(&path.with_file_name("other.key"))
+            .await
+            .unwrap();
+        assert_eq!(reason(verify(&path, &other)), "anchor was modified");
+    }
+}
@@ -0,0 +1,156 @@
+use std::path::{Path, PathBuf};
+
+use manifest::http::HttpResolver;
+
+use crate::{
+    Error,
+    crypto::{Keyring, TrustedKeys},
+    model::{HairpinDaemonOptions, HairpinSourceLocation},
+    policy::Policy,
+};
+
+impl HairpinDaemonOptions {
+    /// Reads a config file, rejecting unknown settings.
+    pub async fn load(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let invalid = |err: &dyn std::fmt::Display| {
+            Error::InvalidConfig(path.display().to_string(), err.to_string())
+        };
+        let document = tokio::fs::read_to_string(path)
+            .await
+            .map_err(|err| invalid(&err))?;
+        toml::from_str(&document).map_err(|err| invalid(&err))
+    }
+    /// Layers these options over the config file given with `--config`, or the default one if
+    /// it exists.
+    pub async fn layered(self) -> Result<Self, Error> {
+        let path = match self.config() {
+            Some(path) => Some(path.to_path_buf()),
+            None => {
+                let path = PathBuf::from(Self::DEFAULT_CONFIG);
+                tokio::fs::try_exists(&path).await?.then_some(path)
+            }
+        };
+        let options = match path {
+            Some(path) => {
+                let base = Self::load(&path).await?;
+                self.over(base)
+            }
+            None => self,
+        };
+        if options.policy().is_some() && !options.reader_uids().is_empty() {
+            return Err(Error::InvalidOption(
+                "reader uids can't be combined with a policy file, which decides readers instead"
+                    .to_string(),
+            ));
+        }
+        Ok(options)
+    }
+    /// Settings that are accepted but have no effect, each with the reason.
+    pub fn warnings(&self) -> Vec<(&'static str, &'static str)> {
+        let mut warnings = Vec::new();
+        if self.poll_rate().is_some() {
+            warnings.push((
+                "poll-rate",
+                "deprecated and ignored, mount changes are read as they happen",
+            ));
+        }
+        warnings
+    }
+    /// Problems with these options, each with the setting it concerns.
+    pub async fn check(&self) -> Vec<(&'static str, Error)> {
+        let mut problems = Vec::new();
+        if self.policy().is_some() && !self.reader_uids().is_empty() {
+            problems.push((
+                "reader-uids",
+                Error::InvalidOption("readers are decided by the policy file instead".to_string()),
+            ));
+        }
+        for scheme in self.allowed_schemes() {
+            if !HairpinSourceLocation::SCHEMES.contains(&scheme.as_str()) {
+                problems.push((
+                    "allowed-schemes",
+                    Error::InvalidOption(format!("unsupported source scheme {scheme:?}")),
+                ));
+            }
+        }
+        if let Some(path) = self.policy()
+            && let Err(err) = Policy::load(path).await
+        {
+            problems.push(("policy", err));
+        }
+        if let Err(err) = TrustedKeys::load(self.trusted_keys()).await {
+            problems.push(("trusted-keys", err));
+        }
+        if let Err(err) = Keyring::load(self.age_identities()).await {
+            problems.push(("age-identities", err));
+        }
+        if let Err(err) = self.mount_trust() {
+            problems.push(("mount-sources", err));
+        }
+        if let Err(err) = HttpResolver::new(&self.http()) {
+            problems.push(("http-ca-bundle", err.into()));
+        }
+        if !is_size(self.delivery_size()) {
+            problems.push((
+                "delivery-size",
+                Error::InvalidOption(format!(
+                    "{:?} isn't a size like 16m or 10%",
+                    self.delivery_size()
+                )),
+            ));
+        }
+        problems
+    }
+}
+/// Whether a tmpfs size is bytes with an optional k, m or g suffix, or a percentage of memory.
+fn is_size(size: &str) -> bool {
+    let digits = size.trim_end_matches(['k', 'm', 'g', 'K', 'M', 'G', '%']);
+    size.len() - digits.len() <= 1
+        && !digits.is_empty()
+        && digits.chars().all(|char| char.is_ascii_digit())
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(document: &str) -> HairpinDaemonOptions {
+        toml::from_str(document).unwrap()
+    }
+    #[test]
+    fn command_line_overrides_config() {
+        let config = options("disable-mounting = true\naudit-chain = true\nreader-uids = [1000]");
+        let layered = options("disable-mounting = false").over(config.clone());
+        assert!(!layered.disable_mounting());
+        assert!(layered.audit_chain());
+        assert_eq!(layered.reader_uids(), [1000]);
+        assert!(
+            HairpinDaemonOptions::default()
+                .over(config)
+                .disable_mounting()
+        );
+    }
+    #[test]
+    fn accepts_deprecated_settings() {
+        assert_eq!(options("poll-rate = 500").poll_rate(), Some(500));
+        assert_eq!(options("poll-rate = 500").warnings().len(), 1);
+        assert!(options("").warnings().is_empty());
+        assert!(toml::from_str::<HairpinDaemonOptions>("poll-rates = 500").is_err());
+    }
+    #[test]
+    fn log_levels() {
+        assert_eq!(options("").log_level(), log::LevelFilter::Info);
+        assert_eq!(
+            options("log-level = \"warn\"").log_level(),
+            log::LevelFilter::Warn
+        );
+        assert!(toml::from_str::<HairpinDaemonOptions>("log-level = \"loud\"").is_err());
+    }
+    #[test]
+    fn readers_dont_replace_policies() {
+        let config = options("policy = \"/etc/hairpin/policy.toml\"");
+        let layered = options("reader-uids = [1000]").over(config);
+        assert!(layered.policy().is_some());
+        assert!(!layered.reader_uids().is_empty());
+    }
+}
@@ -0,0 +1,174 @@
+use std::{
+    collections::HashMap,
+    fmt::Debug,
+    io::Read,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::RwLock,
+};
+
+use manifest::{
+    ItemEncryption, KdfParameters, Manifest,
+    passphrase::{self, KEY_LEN},
+    signature::{self, VerifyingKey},
+};
+use zeroize::Zeroize;
+
+use crate::Error;
+
+/// Key material used to decrypt item values at delivery time.
+#[derive(Default)]
+pub struct Keyring {
+    age: Vec<age::x25519::Identity>,
+    passphrase: RwLock<HashMap<(u64, KdfParameters), LockedKey>>,
+}
+impl Debug for Keyring {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Keyring")
+            .field("age", &self.age.len())
+            .field(
+                "passphrase",
+                &self.passphrase.read().map(|keys| keys.len()).unwrap_or(0),
+            )
+            .finish()
+    }
+}
+/// Derived passphrase key pinned in memory with `mlock` and wiped on drop.
+struct LockedKey(Box<[u8; KEY_LEN]>);
+impl LockedKey {
+    fn new(key: &[u8; KEY_LEN]) -> Result<Self, Error> {
+        let mut locked = Box::new([0u8; KEY_LEN]);
+        if unsafe { libc::mlock(locked.as_ptr().cast(), KEY_LEN) } != 0 {
+            return Err(std::io::Error::last_os_error().into());
+        }
+        locked.copy_from_slice(key);
+        Ok(Self(locked))
+    }
+}
+impl Drop for LockedKey {
+    fn drop(&mut self) {
+        self.0.zeroize();
+        unsafe {
+            libc::munlock(self.0.as_ptr().cast(), KEY_LEN);
+        }
+    }
+}
+impl Keyring {
+    /// Loads X25519 identities from age key files, one `AGE-SECRET-KEY-` per line.
+    pub async fn load(identities: &[PathBuf]) -> Result<Self, Error> {
+        let mut age = Vec::new();
+        for path in identities {
+            age.extend(Self::read_identities(path).await?);
+        }
+        Ok(Self {
+            age,
+            ..Default::default()
+        })
+    }
+    async fn read_identities(path: &Path) -> Result<Vec<age::x25519::Identity>, Error> {
+        tokio::fs::read_to_string(path)
+            .await?
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| {
+                age::x25519::Identity::from_str(line).map_err(|err| {
+                    Error::InvalidIdentity(path.display().to_string(), err.to_string())
+                })
+            })
+            .collect()
+    }
+    /// Keeps the keys derived for a source so its passphrase items can be decrypted.
+    pub fn unlock(
+        &self,
+        source: u64,
+        keys: impl IntoIterator<Item = (KdfParameters, passphrase::Key)>,
+    ) -> Result<(), Error> {
+        let keys = keys
+            .into_iter()
+            .map(|(parameters, key)| Ok(((source, parameters), LockedKey::new(&key)?)))
+            .collect::<Result<Vec<_>, Error>>()?;
+        self.passphrase
+            .write()
+            .map_err(|_| Error::PoisonedKeyring)?
+            .extend(keys);
+        Ok(())
+    }
+    /// Drops every key derived for the given sources.
+    pub fn lock(&self, sources: &[u64]) -> Result<(), Error> {
+        self.passphrase
+            .write()
+            .map_err(|_| Error::PoisonedKeyring)?
+            .retain(|(source, _), _| !sources.contains(source));
+        Ok(())
+    }
+    pub fn decrypt(
+        &self,
+        source: u64,
+        encryption: &ItemEncryption,
+        value: Vec<u8>,
+    ) -> Result<Vec<u8>, Error> {
+        match encryption {
+            ItemEncryption::None | ItemEncryption::PlainText => Ok(value),
+            ItemEncryption::Passphrase(parameters) => {
+                let keys = self.passphrase.read().map_err(|_| Error::PoisonedKeyring)?;
+                let key = keys
+                    .get(&(source, parameters.clone()))
+                    .ok_or(Error::Locked(source))?;
+                Ok(passphrase::open(&key.0, &value)?)
+            }
+            ItemEncryption::Age => {
+                let decryptor = age::Decryptor::new_buffered(value.as_slice())?;
+                let mut reader = decryptor.decrypt(
+                    self.age
+                        .iter()
+                        .map(|identity| identity as &dyn age::Identity),
+                )?;
+                let mut output = Vec::new();
+                reader.read_to_end(&mut output)?;
+                Ok(output)
+            }
+        }
+    }
+}
+/// Publisher keys that source manifests must be signed by. Without any, unsigned sources are accepted.
+#[derive(Debug, Default)]
+pub struct TrustedKeys(Vec<VerifyingKey>);
+impl TrustedKeys {
+    /// Loads hex encoded Ed25519 public keys, one per line.
+    pub async fn load(paths: &[PathBuf]) -> Result<Self, Error> {
+        let mut keys = Vec::new();
+        for path in paths {
+            for line in tokio::fs::read_to_string(path).await?.lines() {
+                let line = line.trim();
+                if line.is_empty() || line.starts_with('#') {
+                    continue;
+                }
+                keys.push(signature::parse_verifying_key(line).map_err(|err| {
+                    Error::InvalidTrustedKey(path.display().to_string(), err.to_string())
+                })?);
+            }
+        }
+        Ok(Self(keys))
+    }
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+    pub fn verify(
+        &self,
+        location: &str,
+        manifest: &Manifest,
+        signature: Option<&str>,
+    ) -> Result<(), Error> {
+        if self.is_empty() {
+            return Ok(());
+        }
+        let signature = signature.ok_or_else(|| {
+            Error::Untrusted(location.to_string(), "manifest is unsigned".to_string())
+        })?;
+        manifest
+            .verify(signature, &self.0)
+            .map_err(|err| Error::Untrusted(location.to_string(), err.to_string()))?;
+        Ok(())
+    }
+}
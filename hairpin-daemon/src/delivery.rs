@@ -0,0 +1,234 @@
+use std::{
+    os::unix::fs::PermissionsExt,
+    path::{Component, Path, PathBuf},
+};
+
+use libmount::{
+    context::{Context, MountFlags},
+    table::Table,
+};
+use tokio::io::AsyncWriteExt;
+
+use crate::Error;
+
+/// Private tmpfs that decrypted item values are materialized into, one directory per source.
+#[derive(Debug)]
+pub struct Delivery {
+    target: PathBuf,
+}
+impl Delivery {
+    pub const FSTYPE: &'static str = "tmpfs";
+    pub const SOURCE: &'static str = "hairpin";
+    /// Mounts a fresh tmpfs at `target`, first unmounting any left behind by a previous run.
+    pub async fn mount(target: impl AsRef<Path>, size: &str) -> Result<Self, Error> {
+        let target = target.as_ref().to_path_buf();
+        tokio::fs::create_dir_all(&target).await?;
+        tokio::fs::set_permissions(&target, std::fs::Permissions::from_mode(0o700)).await?;
+        let options = format!("mode=0700,size={size}");
+        let mount_target = tokio::fs::canonicalize(&target).await?;
+        tokio::task::spawn_blocking(move || -> Result<(), libmount::error::Error> {
+            while Self::is_mounted(&mount_target)? {
+                let mut context = Context::new()?;
+                context.set_target(&mount_target)?.disable_mtab(true)?;
+                context.umount()?;
+            }
+            let mut context = Context::new()?;
+            context
+                .set_source(Self::SOURCE)?
+                .set_target(&mount_target)?
+                .set_fstype(Self::FSTYPE)?
+                .set_options(&options)?
+                .set_mount_flags(MountFlags::NOEXEC | MountFlags::NOSUID | MountFlags::NODEV)?
+                .disable_mtab(true)?;
+            context.mount()
+        })
+        .await
+        .map_err(std::io::Error::other)??;
+        Ok(Self { target })
+    }
+    /// Whether one of our tmpfs mounts is on top of `target`.
+    fn is_mounted(target: &Path) -> Result<bool, libmount::error::Error> {
+        let table = Table::read("/proc/self/mountinfo")?;
+        let mut mounted = false;
+        for filesystem in table.iter()? {
+            let filesystem = filesystem?;
+            if filesystem.target() == Some(target) {
+                mounted = filesystem.source() == Some(Self::SOURCE)
+                    && filesystem.fstype() == Some(Self::FSTYPE);
+            }
+        }
+        Ok(mounted)
+    }
+    pub fn target(&self) -> &Path {
+        &self.target
+    }
+    pub fn source_dir(&self, id: u64) -> PathBuf {
+        self.target.join(id.to_string())
+    }
+    pub fn path(&self, id: u64, name: &str) -> PathBuf {
+        self.source_dir(id).join(name)
+    }
+    /// Names of the values currently delivered for a source.
+    pub async fn delivered(&self, id: u64) -> Result<Vec<String>, Error> {
+        let mut entries = match tokio::fs::read_dir(self.source_dir(id)).await {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(err.into()),
+        };
+        let mut names = Vec::new();
+        while let Some(entry) = entries.next_entry().await? {
+            if let Some(name) = entry.file_name().to_str()
+                && is_file_name(name)
+            {
+                names.push(name.to_string());
+            }
+        }
+        names.sort();
+        Ok(names)
+    }
+    /// Writes an item value readable only by the daemon user, replacing any previous value.
+    pub async fn write(&self, id: u64, name: &str, value: &[u8]) -> Result<(), Error> {
+        if !is_file_name(name) {
+            return Err(Error::UndeliverableItem(name.to_string()));
+        }
+        let dir = self.source_dir(id);
+        tokio::fs::create_dir_all(&dir).await?;
+        tokio::fs::set_permissions(&dir, std::fs::Permissions::from_mode(0o700)).await?;
+        let staging = dir.join(format!(".{name}.tmp"));
+        let mut file = tokio::fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .mode(0o600)
+            .open(&staging)
+            .await?;
+        file.write_all(value).await?;
+        file.sync_all().await?;
+        tokio::fs::rename(&staging, dir.join(name)).await?;
+        Ok(())
+    }
+    /// Writes a value unless the delivered one is the same, returning whether it was written.
+    pub async fn update(&self, id: u64, name: &str, value: &[u8]) -> Result<bool, Error> {
+        match tokio::fs::read(self.path(id, name)).await {
+            Ok(current) if current == value => Ok(false),
+            _ => self.write(id, name, value).await.map(|_| true),
+        }
+    }
+    /// Removes a delivered item value, returning whether there was one.
+    pub async fn remove(&self, id: u64, name: &str) -> Result<bool, Error> {
+        if !is_file_name(name) {
+            return Ok(false);
+        }
+        match tokio::fs::remove_file(self.path(id, name)).await {
+            Ok(()) => Ok(true),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(false),
+            Err(err) => Err(err.into()),
+        }
+    }
+    pub async fn revoke(&self, ids: &[u64]) -> Result<(), Error> {
+        for id in ids {
+            match tokio::fs::remove_dir_all(self.source_dir(*id)).await {
+                Err(err) if err.kind() != std::io::ErrorKind::NotFound => return Err(err.into()),
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+    pub async fn unmount(&self) -> Result<(), Error> {
+        let target = self.target.clone();
+        tokio::task::spawn_blocking(move || -> Result<(), libmount::error::Error> {
+            let mut context = Context::new()?;
+            context.set_target(&target)?.disable_mtab(true)?;
+            context.umount()
+        })
+        .await
+        .map_err(std::io::Error::other)??;
+        Ok(())
+    }
+}
+fn is_file_name(name: &str) -> bool {
+    let mut components = Path::new(name).components();
+    matches!(
+        (components.next(), components.next()),
+        (Some(Component::Normal(_)), None)
+    ) && !name.starts_with('.')
+}
+#[cfg(test)]
+mod tests {
+    use std::{os::unix::fs::MetadataExt, process::Command};
+
+    use super::*;
+
+    const NAMESPACE: &str = "HAIRPIN_TEST_NAMESPACE";
+
+    /// Runs a test again in an unprivileged user and mount namespace, returning whether it's
+    /// already running there. Without user namespaces, the test is skipped.
+    fn in_namespace(test: &str) -> bool {
+        if std::env::var_os(NAMESPACE).is_some() {
+            return true;
+        }
+        let unshare = || {
+            let mut command = Command::new("unshare");
+            command.args(["--user", "--map-root-user", "--mount"]);
+            command
+        };
+        if !unshare()
+            .arg("true")
+            .status()
+            .is_ok_and(|status| status.success())
+        {
+            eprintln!("Skipping {test}, user namespaces are unavailable");
+            return false;
+        }
+        let status = unshare()
+            .arg(std::env::current_exe().unwrap())
+            .args(["--exact", test, "--test-threads", "1"])
+            .env(NAMESPACE, "1")
+            .status()
+            .unwrap();
+        assert!(status.success(), "{test} failed in a user namespace");
+        false
+    }
+    fn mounts(target: &Path) -> usize {
+        std::fs::read_to_string("/proc/self/mountinfo")
+            .unwrap()
+            .lines()
+            .filter(|line| line.split(' ').nth(4) == Some(target.to_str().unwrap()))
+            .count()
+    }
+    #[test]
+    fn delivers_into_a_private_tmpfs() {
+        if !in_namespace("delivery::tests::delivers_into_a_private_tmpfs") {
+            return;
+        }
+        let target = std::env::temp_dir().join(format!("hairpin-delivery-{}", std::process::id()));
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            let delivery = Delivery::mount(&target, "1m").await.unwrap();
+            let target = delivery.target().canonicalize().unwrap();
+            assert_eq!(mounts(&target), 1);
+            assert!(delivery.update(1, "token", b"secret").await.unwrap());
+            assert!(!delivery.update(1, "token", b"secret").await.unwrap());
+            let path = delivery.path(1, "token");
+            assert_eq!(std::fs::read(&path).unwrap(), b"secret");
+            assert_eq!(std::fs::metadata(&path).unwrap().mode() & 0o777, 0o600);
+            assert_eq!(delivery.delivered(1).await.unwrap(), vec!["token"]);
+            for name in ["../escape", ".hidden", "a/b"] {
+                assert!(matches!(
+                    delivery.write(1, name, b"secret").await,
+                    Err(Error::UndeliverableItem(_))
+                ));
+            }
+            // A daemon that crashed leaves its tmpfs behind, which is replaced rather than stacked.
+            let restarted = Delivery::mount(&target, "1m").await.unwrap();
+            assert_eq!(mounts(&target), 1);
+            assert!(restarted.delivered(1).await.unwrap().is_empty());
+            restarted.unmount().await.unwrap();
+            assert_eq!(mounts(&target), 0);
+        });
+        let _ = std::fs::remove_dir(&target);
+    }
+}
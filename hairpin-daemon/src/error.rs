@@ -1,3 +1,5 @@
+use std::path::PathBuf;
+
 use http::uri::InvalidUri;
 use tonic::Status;
 
@@ -7,12 +9,128 @@ pub enum Error {
     ProhibitedUri(String),
     #[error(transparent)]
     InvalidManifest(#[from] manifest::path::Error),
+    #[error(transparent)]
+    IO(#[from] std::io::Error),
+    #[error(transparent)]
+    Transport(#[from] tonic::transport::Error),
+    #[error("Invalid daemon state: {0}")]
+    InvalidState(#[from] toml::de::Error),
+    #[error(transparent)]
+    StateSerialization(#[from] toml::ser::Error),
+    #[error("Error monitoring mounts: {0}")]
+    MountMonitor(String),
+    #[error("Invalid identity in {0}: {1}")]
+    InvalidIdentity(String, String),
+    #[error("Unable to decrypt item: {0}")]
+    Decryption(#[from] age::DecryptError),
+    #[error("Unable to decrypt item: {0}")]
+    PassphraseDecryption(#[from] manifest::passphrase::Error),
+    #[error("Source {0} is locked")]
+    Locked(u64),
+    #[error("Source {0} has no passphrase encrypted items")]
+    NotLocked(u64),
+    #[error("Incorrect passphrase for source {0}")]
+    IncorrectPassphrase(u64),
+    #[error("Source {0} does not exist")]
+    UnknownSource(u64),
+    #[error("Source {0} has no item {1}")]
+    UnknownItem(u64, String),
+    #[error("Invalid request: {0}")]
+    InvalidRequest(&'static str),
+    #[error("Permission denied: {0}")]
+    Denied(String),
+    #[error("Invalid policy in {0}: {1}")]
+    InvalidPolicy(String, String),
+    #[error("Keyring lock poisoned")]
+    PoisonedKeyring,
+    #[error("Item {0} can't be delivered, its name must be a plain file name")]
+    UndeliverableItem(String),
+    #[error("Error mounting delivery target: {0}")]
+    Delivery(#[from] libmount::error::Error),
+    #[error(transparent)]
+    Remote(#[from] manifest::http::Error),
+    #[error("Unable to read item value: {0}")]
+    Value(#[from] manifest::value::Error),
+    #[error("Invalid trusted key in {0}: {1}")]
+    InvalidTrustedKey(String, String),
+    #[error("Source {0} is untrusted: {1}")]
+    Untrusted(String, String),
+    #[error(transparent)]
+    AuditSerialization(#[from] serde_json::Error),
+    #[error("Unable to render template: {0}")]
+    Template(#[from] manifest::template::Error),
+    #[error("Invalid config {0}: {1}")]
+    InvalidConfig(String, String),
+    #[error("{0}")]
+    InvalidOption(String),
+    #[error("Invalid audit key {0:?}, expected 32 hex encoded bytes")]
+    InvalidAuditKey(PathBuf),
+    #[error("Audit log {0} line {1}: {2}")]
+    BrokenAuditChain(String, usize, &'static str),
+    #[error("Socket {0:?} is in use or isn't a socket")]
+    SocketInUse(PathBuf),
+    #[error("Watcher missed {0} events, watch again to resynchronize")]
+    WatchLagged(u64),
 }
 impl From<Error> for Status {
     fn from(value: Error) -> Self {
         match &value {
             Error::ProhibitedUri(_) => Status::permission_denied(value.to_string()),
             Error::InvalidManifest(_) => Status::invalid_argument(value.to_string()),
+            Error::IO(_)
+            | Error::Transport(_)
+            | Error::InvalidState(_)
+            | Error::StateSerialization(_)
+            | Error::MountMonitor(_)
+            | Error::InvalidIdentity(_, _)
+            | Error::InvalidTrustedKey(_, _)
+            | Error::InvalidPolicy(_, _)
+            | Error::AuditSerialization(_)
+            | Error::BrokenAuditChain(_, _, _)
+            | Error::InvalidAuditKey(_)
+            | Error::SocketInUse(_)
+            | Error::InvalidConfig(_, _)
+            | Error::InvalidOption(_)
+            | Error::PoisonedKeyring
+            | Error::Delivery(_) => Status::internal(value.to_string()),
+            Error::Decryption(_)
+            | Error::PassphraseDecryption(_)
+            | Error::Locked(_)
+            | Error::NotLocked(_)
+            | Error::UndeliverableItem(_)
+            | Error::Template(_) => Status::failed_precondition(value.to_string()),
+            Error::InvalidRequest(_) => Status::invalid_argument(value.to_string()),
+            Error::WatchLagged(_) => Status::resource_exhausted(value.to_string()),
+            Error::IncorrectPassphrase(_) | Error::Untrusted(_, _) | Error::Denied(_) => {
+                Status::permission_denied(value.to_string())
+            }
+            Error::UnknownSource(_) | Error::UnknownItem(_, _) => {
+                Status::not_found(value.to_string())
+            }
+            Error::Remote(err) => match err {
+                manifest::http::Error::InvalidUrl(_, _)
+                | manifest::http::Error::Escapes(_)
+                | manifest::http::Error::InvalidManifest(_)
+                | manifest::http::Error::Validation(_) => {
+                    Status::invalid_argument(value.to_string())
+                }
+                manifest::http::Error::CaBundle(_, _) => Status::internal(value.to_string()),
+                manifest::http::Error::Request(_)
+                | manifest::http::Error::Status(_, _)
+                | manifest::http::Error::TooLarge(_, _) => Status::unavailable(value.to_string()),
+            },
+            Error::Value(err) => match err {
+                manifest::value::Error::IO(_) => Status::internal(value.to_string()),
+                manifest::value::Error::Http(_) => Status::unavailable(value.to_string()),
+                manifest::value::Error::Escapes(_) => Status::invalid_argument(value.to_string()),
+                manifest::value::Error::RemoteDenied(_, _) => {
+                    Status::permission_denied(value.to_string())
+                }
+                manifest::value::Error::RemoteUnavailable(_)
+                | manifest::value::Error::Digest { .. } => {
+                    Status::failed_precondition(value.to_string())
+                }
+            },
         }
     }
 }
@@ -0,0 +1,79 @@
+use std::{path::PathBuf, time::SystemTime};
+
+use manifest::Manifest;
+use tokio::sync::broadcast;
+
+use crate::{model::SourceOrigin, reload::ItemDiff};
+
+/// What happened to a source.
+#[derive(Debug, Clone)]
+pub enum SourceChange {
+    /// Announced before the source's items are delivered.
+    Added {
+        location: String,
+        origin: SourceOrigin,
+    },
+    /// Announced after every delivered value of the source was revoked.
+    Removed {
+        location: String,
+    },
+    ManifestChanged(ItemDiff),
+    /// A value was written to the delivery target, `item` being `None` for rendered templates.
+    DeliveryMaterialized {
+        name: String,
+        path: PathBuf,
+        item: Option<String>,
+    },
+    DeliveryRevoked {
+        name: String,
+        path: PathBuf,
+    },
+}
+/// A change to a source, with the manifest it had at the time.
+#[derive(Debug, Clone)]
+pub struct SourceEvent {
+    source: u64,
+    manifest: Manifest,
+    time: SystemTime,
+    change: SourceChange,
+}
+impl SourceEvent {
+    pub fn source(&self) -> u64 {
+        self.source
+    }
+    pub fn manifest(&self) -> &Manifest {
+        &self.manifest
+    }
+    pub fn time(&self) -> SystemTime {
+        self.time
+    }
+    pub fn change(&self) -> &SourceChange {
+        &self.change
+    }
+}
+/// Broadcasts source events to every watcher. Watchers falling more than [`Self::CAPACITY`]
+/// events behind miss the oldest ones.
+#[derive(Debug)]
+pub struct SourceEvents(broadcast::Sender<SourceEvent>);
+impl SourceEvents {
+    pub const CAPACITY: usize = 256;
+    pub fn subscribe(&self) -> broadcast::Receiver<SourceEvent> {
+        self.0.subscribe()
+    }
+    /// Sends an event, cloning the manifest only when someone is watching.
+    pub fn send(&self, source: u64, manifest: &Manifest, change: SourceChange) {
+        if self.0.receiver_count() > 0 {
+            let _ = self.0.send(SourceEvent {
+                source,
+                manifest: manifest.clone(),
+                time: SystemTime::now(),
+                change,
+            });
+        }
+    }
+}
+impl Default for SourceEvents {
+    fn default() -> Self {
+        Self(broadcast::channel(Self::CAPACITY).0)
+    }
+}
@@ -1,15 +1,82 @@
 use std::sync::Arc;
 
+use audit::{AuditKey, AuditLog};
+use crypto::{Keyring, TrustedKeys};
+use delivery::Delivery;
+use manifest::http::HttpResolver;
 use model::{HairpinDaemon, HairpinDaemonOptions};
+use policy::Policy;
+pub mod audit;
+mod config;
+pub mod crypto;
+pub mod delivery;
 mod error;
+pub mod events;
+mod logger;
 pub mod model;
+mod mount;
+pub mod policy;
+pub mod reload;
+mod server;
 pub mod service;
+pub mod state;
 pub use error::*;
 
 impl HairpinDaemon {
     pub async fn start(options: HairpinDaemonOptions) -> Result<(), Error> {
-        let daemon = Arc::new(HairpinDaemon::default());
-
-        Ok(())
+        let options = options.layered().await?;
+        logger::init(options.log_level());
+        if options.poll_rate().is_some() {
+            log::warn!(
+                "poll-rate is deprecated and ignored, mount changes are read as they happen"
+            );
+        }
+        let mut daemon = HairpinDaemon::restore(
+            options.state_dir(),
+            HttpResolver::new(&options.http())?,
+            TrustedKeys::load(options.trusted_keys()).await?,
+        )
+        .await?
+        .with_keyring(Keyring::load(options.age_identities()).await?)
+        .with_remote_policy(options.remote_policy())
+        .with_policy(match options.policy() {
+            Some(path) => Policy::load(path).await?,
+            None => Policy::local(options.reader_uids().iter().copied()),
+        });
+        if let Some(path) = options.audit_log() {
+            let key = match options.audit_chain() {
+                true => Some(AuditKey::load_or_create(&options.audit_key()).await?),
+                false => None,
+            };
+            daemon = daemon.with_audit(
+                AuditLog::open(path, options.audit_max_size(), options.audit_keep(), key)
+                    .await?
+                    .with_fail_closed(options.audit_fail_closed()),
+            );
+        }
+        if let Some(target) = options.delivery_target() {
+            if options.disable_mounting() {
+                log::warn!("Mounting is disabled, items won't be delivered to {target:?}");
+            } else {
+                daemon =
+                    daemon.with_delivery(Delivery::mount(target, options.delivery_size()).await?);
+            }
+        }
+        let daemon = Arc::new(daemon);
+        let shutdown = daemon.shutdown().clone();
+        let result = async {
+            daemon.deliver_all().await?;
+            tokio::try_join!(
+                server::shutdown_signal(shutdown.clone()),
+                server::serve_unix(&daemon, &options, shutdown.clone()),
+                server::serve_tcp(&daemon, &options, shutdown.clone()),
+                mount::watch_mounts(&daemon, &options, shutdown.clone()),
+                reload::watch_sources(&daemon, &options, shutdown.clone()),
+            )
+        }
+        .await;
+        let closed = daemon.close().await;
+        result?;
+        closed
     }
 }
@@ -0,0 +1,31 @@
+use std::io::Write;
+
+use log::{LevelFilter, Log, Metadata, Record};
+
+/// Writes operational messages to stderr as `LEVEL target: message`, one per line, so they can
+/// be filtered by level or module.
+struct StderrLogger;
+impl Log for StderrLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+    fn log(&self, record: &Record) {
+        if self.enabled(record.metadata()) {
+            let _ = writeln!(
+                std::io::stderr().lock(),
+                "{:<5} {}: {}",
+                record.level(),
+                record.target(),
+                record.args()
+            );
+        }
+    }
+    fn flush(&self) {}
+}
+static LOGGER: StderrLogger = StderrLogger;
+/// Installs the daemon logger, unless the embedding program installed its own.
+pub(crate) fn init(level: LevelFilter) {
+    if log::set_logger(&LOGGER).is_ok() {
+        log::set_max_level(level);
+    }
+}
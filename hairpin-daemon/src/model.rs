@@ -1,24 +1,125 @@
 use std::{
-    collections::BTreeMap,
+    collections::{BTreeMap, HashMap},
+    ffi::OsStr,
+    fmt::Display,
+    net::SocketAddr,
+    os::unix::ffi::OsStrExt,
     path::{Path, PathBuf},
-    sync::atomic::AtomicU64,
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
 };
 
 use http::Uri;
-use manifest::{Manifest, ManifestResolver};
-use tokio::sync::RwLock;
+use log::LevelFilter;
+use manifest::{
+    Item, ItemEncryption, Manifest, ManifestResolver, Template,
+    http::{HttpOptions, HttpResolver},
+    template::TemplateDocument,
+    value::{RemotePolicy, ValueBase, ValueResolver},
+};
+use percent_encoding::{AsciiSet, NON_ALPHANUMERIC, percent_decode_str, percent_encode};
+use serde::Deserialize;
+use tokio::sync::{Notify, RwLock};
+use tokio_util::sync::CancellationToken;
+use zeroize::Zeroizing;
 
-use crate::Error;
+use crate::{
+    Error,
+    audit::{AuditEvent, AuditLog},
+    crypto::{Keyring, TrustedKeys},
+    delivery::Delivery,
+    events::{SourceChange, SourceEvents},
+    mount::MountTrust,
+    policy::{Action, Caller, Policy},
+    state::StateStore,
+};
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct HairpinSource {
     location: HairpinSourceLocation,
     manifest: Manifest,
+    origin: SourceOrigin,
 }
 
 impl HairpinSource {
     pub fn new(location: HairpinSourceLocation, manifest: Manifest) -> Self {
-        Self { location, manifest }
+        let origin = match &location {
+            HairpinSourceLocation::Local(_) => SourceOrigin::InternalDisk,
+            HairpinSourceLocation::Remote(_) => SourceOrigin::Remote,
+        };
+        Self {
+            location,
+            manifest,
+            origin,
+        }
+    }
+    pub fn with_origin(mut self, origin: SourceOrigin) -> Self {
+        self.origin = origin;
+        self
+    }
+    pub fn location(&self) -> &HairpinSourceLocation {
+        &self.location
+    }
+    pub fn origin(&self) -> SourceOrigin {
+        self.origin
+    }
+    pub fn manifest(&self) -> &Manifest {
+        &self.manifest
+    }
+    /// Where relative paths of the source are read from, `location` holding the url of remote ones.
+    fn base<'a>(&'a self, location: &'a mut String) -> ValueBase<'a> {
+        match &self.location {
+            HairpinSourceLocation::Local(root) if root.is_dir() => ValueBase::Dir(root),
+            HairpinSourceLocation::Local(root) => {
+                ValueBase::Dir(root.parent().unwrap_or(Path::new("/")))
+            }
+            HairpinSourceLocation::Remote(uri) => {
+                *location = uri.to_string();
+                ValueBase::Url(location)
+            }
+        }
+    }
+    /// Reads an item's raw value, fetching values of remote sources relative to the manifest url.
+    /// Remote values are only fetched as `policy` allows.
+    pub async fn read_item(
+        &self,
+        item: &Item,
+        remote: &HttpResolver,
+        policy: &RemotePolicy,
+    ) -> Result<Vec<u8>, Error> {
+        let mut location = String::new();
+        Ok(ValueResolver::new(self.base(&mut location))
+            .with_http(remote)
+            .with_policy(policy)
+            .fetch_item(item)
+            .await?)
+    }
+    pub async fn read_template(
+        &self,
+        template: &Template,
+        remote: &HttpResolver,
+    ) -> Result<TemplateDocument, Error> {
+        let mut location = String::new();
+        let text = ValueResolver::new(self.base(&mut location))
+            .with_http(remote)
+            .fetch_template(template)
+            .await?;
+        let text = String::from_utf8(text).map_err(|_| manifest::template::Error::Encoding)?;
+        Ok(TemplateDocument::parse(&text)?)
+    }
+}
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum SourceOrigin {
+    #[default]
+    Unknown,
+    InternalDisk,
+    ExternalDisk,
+    Remote,
+}
+impl SourceOrigin {
+    /// Sources that come and go with their filesystem aren't written to the state file.
+    pub fn is_persistent(&self) -> bool {
+        !matches!(self, SourceOrigin::ExternalDisk)
     }
 }
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -26,7 +127,15 @@ pub enum HairpinSourceLocation {
     Local(PathBuf),
     Remote(Uri),
 }
+/// Everything but unreserved characters and separators is escaped in `file` uri paths.
+const PATH: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'/')
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~');
 impl HairpinSourceLocation {
+    pub const SCHEMES: &'static [&'static str] = &["file", "http", "https"];
     pub fn priority(&self) -> usize {
         match self {
             HairpinSourceLocation::Local(_) => 0,
@@ -44,13 +153,15 @@ impl PartialOrd for HairpinSourceLocation {
         Some(self.priority().cmp(&other.priority()))
     }
 }
-impl ManifestResolver for HairpinSourceLocation {
-    type Error = Error;
-
-    async fn resolve(&self) -> Result<Manifest, Self::Error> {
+impl Display for HairpinSourceLocation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            HairpinSourceLocation::Local(value) => Ok(value.resolve().await?),
-            HairpinSourceLocation::Remote(uri) => todo!(),
+            HairpinSourceLocation::Local(path) => write!(
+                f,
+                "file://localhost{}",
+                percent_encode(path.as_os_str().as_bytes(), PATH)
+            ),
+            HairpinSourceLocation::Remote(uri) => write!(f, "{uri}"),
         }
     }
 }
@@ -62,8 +173,12 @@ impl TryFrom<Uri> for HairpinSourceLocation {
         if let Some(scheme) = value.scheme_str() {
             match scheme {
                 "file" => Ok(HairpinSourceLocation::Local(
-                    Path::new(value.path()).to_path_buf(),
+                    Path::new(OsStr::from_bytes(
+                        &percent_decode_str(value.path()).collect::<Vec<_>>(),
+                    ))
+                    .to_path_buf(),
                 )),
+                "http" | "https" => Ok(HairpinSourceLocation::Remote(value)),
                 _ => Err(crate::Error::ProhibitedUri(value.to_string())),
             }
         } else {
@@ -71,24 +186,844 @@ impl TryFrom<Uri> for HairpinSourceLocation {
         }
     }
 }
-#[derive(Debug, Default)]
+#[derive(Debug)]
 pub struct HairpinDaemon {
     counter: AtomicU64,
     manifests: RwLock<BTreeMap<u64, RwLock<HairpinSource>>>,
+    state: Option<StateStore>,
+    keyring: Keyring,
+    delivery: Option<Delivery>,
+    remote: HttpResolver,
+    remote_policy: RemotePolicy,
+    trusted: TrustedKeys,
+    policy: Policy,
+    audit: Option<AuditLog>,
+    registered: Notify,
+    events: SourceEvents,
+    shutdown: CancellationToken,
 }
 
 impl HairpinDaemon {
+    /// Opens the state directory and re-registers every persisted source under its original id.
+    pub async fn restore(
+        state_dir: impl AsRef<Path>,
+        remote: HttpResolver,
+        trusted: TrustedKeys,
+    ) -> Result<Self, Error> {
+        let store = StateStore::open(state_dir).await?;
+        let state = store.state().await;
+        let mut daemon = Self {
+            counter: AtomicU64::new(state.next_id()),
+            manifests: RwLock::default(),
+            state: None,
+            keyring: Keyring::default(),
+            delivery: None,
+            remote,
+            remote_policy: RemotePolicy::default(),
+            trusted,
+            policy: Policy::default(),
+            audit: None,
+            registered: Notify::new(),
+            events: SourceEvents::default(),
+            shutdown: CancellationToken::new(),
+        };
+        let mut manifests = BTreeMap::new();
+        for source in state.sources() {
+            let resolved = match source.location() {
+                Ok(location) => daemon
+                    .resolve(&location)
+                    .await
+                    .map(|manifest| HairpinSource::new(location, manifest)),
+                Err(err) => Err(err),
+            };
+            match resolved {
+                Ok(resolved) => {
+                    manifests.insert(source.id(), RwLock::new(resolved));
+                }
+                Err(err) => log::error!("Unable to restore source {}: {err}", source.id()),
+            }
+        }
+        daemon.manifests = RwLock::new(manifests);
+        daemon.state = Some(store);
+        Ok(daemon)
+    }
+    /// Resolves a manifest, rejecting it unless it's signed by a trusted key when any are configured.
+    pub async fn resolve(&self, location: &HairpinSourceLocation) -> Result<Manifest, Error> {
+        let (manifest, signature) = match location {
+            HairpinSourceLocation::Local(path) => (
+                path.resolve().await?,
+                match self.trusted.is_empty() {
+                    true => None,
+                    false => manifest::path::signature(path).await?,
+                },
+            ),
+            HairpinSourceLocation::Remote(uri) => {
+                let uri = uri.to_string();
+                (
+                    self.remote.manifest(&uri).await?,
+                    match self.trusted.is_empty() {
+                        true => None,
+                        false => self.remote.signature(&uri).await?,
+                    },
+                )
+            }
+        };
+        self.trusted
+            .verify(&location.to_string(), &manifest, signature.as_deref())?;
+        Ok(manifest)
+    }
+    pub async fn read_item(&self, source: &HairpinSource, item: &Item) -> Result<Vec<u8>, Error> {
+        source
+            .read_item(item, &self.remote, &self.remote_policy)
+            .await
+    }
+    pub fn with_remote_policy(mut self, policy: RemotePolicy) -> Self {
+        self.remote_policy = policy;
+        self
+    }
+    pub fn with_keyring(mut self, keyring: Keyring) -> Self {
+        self.keyring = keyring;
+        self
+    }
+    pub fn with_policy(mut self, policy: Policy) -> Self {
+        self.policy = policy;
+        self
+    }
+    pub fn policy(&self) -> &Policy {
+        &self.policy
+    }
+    /// Checks an action against the policy, recording denials.
+    pub async fn authorize(
+        &self,
+        caller: &Caller,
+        action: Action,
+        manifest: &Manifest,
+        item: Option<&Item>,
+    ) -> Result<(), Error> {
+        let result = self.policy.authorize(caller, action, manifest, item);
+        if let Err(err) = &result {
+            self.record(AuditEvent::Denied {
+                caller: caller.clone(),
+                action,
+                manifest: manifest.id().to_string(),
+                item: item.map(|item| item.id().to_string()),
+                reason: err.to_string(),
+            })
+            .await;
+        }
+        result
+    }
+    /// Checks an action on a registered source.
+    pub async fn authorize_source(
+        &self,
+        caller: &Caller,
+        action: Action,
+        id: u64,
+    ) -> Result<(), Error> {
+        let manifests = self.manifests.read().await;
+        let source = manifests
+            .get(&id)
+            .ok_or(Error::UnknownSource(id))?
+            .read()
+            .await;
+        self.authorize(caller, action, source.manifest(), None)
+            .await
+    }
+    pub fn with_audit(mut self, audit: AuditLog) -> Self {
+        self.audit = Some(audit);
+        self
+    }
+    /// Appends to the audit log, if any. Failing to do so doesn't fail the audited operation.
+    pub async fn record(&self, event: AuditEvent) {
+        if let Some(audit) = &self.audit
+            && let Err(err) = audit.record(&event).await
+        {
+            log::error!("Unable to write audit log {:?}: {err}", audit.path());
+        }
+    }
+    /// Records an item value being handed out, failing if that can't be recorded and the audit
+    /// log fails closed.
+    pub async fn record_read(&self, caller: &Caller, id: u64, item: &Item) -> Result<(), Error> {
+        let event = AuditEvent::ItemRead {
+            caller: caller.clone(),
+            source: id,
+            item: item.id().to_string(),
+            name: item.name().to_string(),
+        };
+        if let Some(audit) = &self.audit
+            && let Err(err) = audit.record(&event).await
+        {
+            log::error!("Unable to write audit log {:?}: {err}", audit.path());
+            if audit.fail_closed() {
+                return Err(err);
+            }
+        }
+        Ok(())
+    }
+    pub fn with_delivery(mut self, delivery: Delivery) -> Self {
+        self.delivery = Some(delivery);
+        self
+    }
+    pub fn delivery(&self) -> Option<&Delivery> {
+        self.delivery.as_ref()
+    }
+    /// Materializes every item and template of a source that can currently be decrypted.
+    pub async fn deliver(&self, id: u64, source: &HairpinSource) -> Result<(), Error> {
+        self.deliver_items(id, source, source.manifest().items())
+            .await?;
+        self.deliver_templates(id, source).await
+    }
+    /// Renders a source's templates into the delivery target, leaving out those that fail to
+    /// render or be written.
+    /// Unchanged renderings aren't rewritten.
+    pub async fn deliver_templates(&self, id: u64, source: &HairpinSource) -> Result<(), Error> {
+        let Some(delivery) = &self.delivery else {
+            return Ok(());
+        };
+        for template in source.manifest().templates() {
+            match self.render_template(id, source, template).await {
+                Ok((rendered, items)) => {
+                    match delivery.update(id, template.target(), &rendered).await {
+                        Ok(true) => {
+                            self.record(AuditEvent::TemplateDelivered {
+                                source: id,
+                                target: template.target().to_string(),
+                                items,
+                            })
+                            .await;
+                            self.materialized(id, source.manifest(), template.target(), None);
+                        }
+                        Ok(false) => {}
+                        Err(err) => log::warn!(
+                            "Unable to deliver template {} of source {id}: {err}",
+                            template.target()
+                        ),
+                    }
+                }
+                Err(err) => {
+                    log::warn!(
+                        "Skipping template {} of source {id}: {err}",
+                        template.target()
+                    );
+                    self.revoke(id, source.manifest(), template.target())
+                        .await?;
+                }
+            }
+        }
+        Ok(())
+    }
+    /// Renders a template, returning it with the ids of the items whose values it holds.
+    pub async fn render_template(
+        &self,
+        id: u64,
+        source: &HairpinSource,
+        template: &Template,
+    ) -> Result<(Vec<u8>, Vec<String>), Error> {
+        let document = source.read_template(template, &self.remote).await?;
+        let items = document.items(source.manifest())?;
+        let mut values = HashMap::new();
+        for item in &items {
+            values.insert(
+                item.id().to_string(),
+                self.decrypt_item(id, source, item).await?,
+            );
+        }
+        let rendered = document.render(source.manifest(), &values)?;
+        Ok((
+            rendered,
+            items.iter().map(|item| item.id().to_string()).collect(),
+        ))
+    }
+    /// Materializes the given items of a source, skipping those that can't be decrypted or
+    /// written. Values that are already delivered as they are aren't rewritten.
+    pub async fn deliver_items(
+        &self,
+        id: u64,
+        source: &HairpinSource,
+        items: impl IntoIterator<Item = &Item>,
+    ) -> Result<(), Error> {
+        let Some(delivery) = &self.delivery else {
+            return Ok(());
+        };
+        for item in items {
+            match self.decrypt_item(id, source, item).await {
+                Ok(value) => match delivery.update(id, item.name(), &value).await {
+                    Ok(true) => {
+                        self.record(AuditEvent::ItemDelivered {
+                            source: id,
+                            item: item.id().to_string(),
+                            name: item.name().to_string(),
+                        })
+                        .await;
+                        self.materialized(id, source.manifest(), item.name(), Some(item.id()));
+                    }
+                    Ok(false) => {}
+                    Err(err) => {
+                        log::warn!(
+                            "Unable to deliver item {} of source {id}: {err}",
+                            item.name()
+                        )
+                    }
+                },
+                Err(err) => {
+                    log::warn!("Skipping item {} of source {id}: {err}", item.name());
+                    self.revoke(id, source.manifest(), item.name()).await?;
+                }
+            }
+        }
+        Ok(())
+    }
+    fn materialized(&self, id: u64, manifest: &Manifest, name: &str, item: Option<&str>) {
+        if let Some(delivery) = &self.delivery {
+            self.events.send(
+                id,
+                manifest,
+                SourceChange::DeliveryMaterialized {
+                    name: name.to_string(),
+                    path: delivery.path(id, name),
+                    item: item.map(str::to_string),
+                },
+            );
+        }
+    }
+    /// Removes a delivered value, announcing it if there was one.
+    pub async fn revoke(&self, id: u64, manifest: &Manifest, name: &str) -> Result<(), Error> {
+        if let Some(delivery) = &self.delivery
+            && delivery.remove(id, name).await?
+        {
+            self.events.send(
+                id,
+                manifest,
+                SourceChange::DeliveryRevoked {
+                    name: name.to_string(),
+                    path: delivery.path(id, name),
+                },
+            );
+        }
+        Ok(())
+    }
+    pub async fn deliver_all(&self) -> Result<(), Error> {
+        let manifests = self.manifests.read().await;
+        for (id, source) in manifests.iter() {
+            self.deliver(*id, &*source.read().await).await?;
+        }
+        Ok(())
+    }
+    /// Unmounts the delivery target, discarding every materialized value.
+    pub async fn close(&self) -> Result<(), Error> {
+        match &self.delivery {
+            Some(delivery) => delivery.unmount().await,
+            None => Ok(()),
+        }
+    }
+    /// Reads an item's value and decrypts it in memory.
+    pub async fn decrypt_item(
+        &self,
+        id: u64,
+        source: &HairpinSource,
+        item: &Item,
+    ) -> Result<Vec<u8>, Error> {
+        self.keyring
+            .decrypt(id, item.encryption(), self.read_item(source, item).await?)
+    }
+    /// Decrypts the items of a source selected by id or name and by label.
+    /// Fails if any selection matches nothing or any selected item can't be decrypted.
+    pub async fn get_items(
+        &self,
+        caller: &Caller,
+        id: u64,
+        keys: &[String],
+        labels: &[String],
+    ) -> Result<Vec<(Item, Vec<u8>)>, Error> {
+        let manifests = self.manifests.read().await;
+        let source = manifests
+            .get(&id)
+            .ok_or(Error::UnknownSource(id))?
+            .read()
+            .await;
+        let items = source.manifest().items();
+        let mut selected = Vec::<&Item>::new();
+        for key in keys {
+            let item = items
+                .iter()
+                .find(|item| item.id() == key)
+                .or_else(|| items.iter().find(|item| item.name() == key))
+                .ok_or_else(|| Error::UnknownItem(id, key.clone()))?;
+            if !selected.iter().any(|value| value.id() == item.id()) {
+                selected.push(item);
+            }
+        }
+        for label in labels {
+            let mut labeled = items
+                .iter()
+                .filter(|item| item.labels().contains(label))
+                .peekable();
+            if labeled.peek().is_none() {
+                return Err(Error::UnknownItem(id, format!("labeled {label}")));
+            }
+            for item in labeled {
+                if !selected.iter().any(|value| value.id() == item.id()) {
+                    selected.push(item);
+                }
+            }
+        }
+        for item in &selected {
+            self.authorize(caller, Action::Read, source.manifest(), Some(item))
+                .await?;
+        }
+        let mut output = Vec::with_capacity(selected.len());
+        for item in selected {
+            output.push((item.clone(), self.decrypt_item(id, &source, item).await?));
+        }
+        for (item, _) in &output {
+            self.record_read(caller, id, item).await?;
+        }
+        Ok(output)
+    }
+    /// Decrypts the first item of a source accepted by `select`, reporting `key` if there's none.
+    pub async fn get_item(
+        &self,
+        caller: &Caller,
+        id: u64,
+        key: &str,
+        select: impl Fn(&Item) -> bool,
+    ) -> Result<Vec<u8>, Error> {
+        let manifests = self.manifests.read().await;
+        let source = manifests
+            .get(&id)
+            .ok_or(Error::UnknownSource(id))?
+            .read()
+            .await;
+        let item = source
+            .manifest()
+            .items()
+            .iter()
+            .find(|item| select(item))
+            .ok_or_else(|| Error::UnknownItem(id, key.to_string()))?;
+        self.authorize(caller, Action::Read, source.manifest(), Some(item))
+            .await?;
+        let value = self.decrypt_item(id, &source, item).await?;
+        self.record_read(caller, id, item).await?;
+        Ok(value)
+    }
+    /// Derives the keys for a source's passphrase items, checking each against one of its items.
+    pub async fn unlock(&self, id: u64, passphrase: Zeroizing<String>) -> Result<(), Error> {
+        let manifests = self.manifests.read().await;
+        let source = manifests
+            .get(&id)
+            .ok_or(Error::UnknownSource(id))?
+            .read()
+            .await;
+        let mut samples = HashMap::new();
+        for item in source.manifest().items() {
+            if let ItemEncryption::Passphrase(parameters) = item.encryption()
+                && !samples.contains_key(parameters)
+            {
+                samples.insert(parameters.clone(), self.read_item(&source, item).await?);
+            }
+        }
+        if samples.is_empty() {
+            return Err(Error::NotLocked(id));
+        }
+        let keys = tokio::task::spawn_blocking(move || {
+            samples
+                .into_iter()
+                .map(|(parameters, sample)| {
+                    let key = parameters.derive(passphrase.as_bytes())?;
+                    manifest::passphrase::open(&key, &sample)
+                        .map_err(|_| Error::IncorrectPassphrase(id))?;
+                    Ok((parameters, key))
+                })
+                .collect::<Result<Vec<_>, Error>>()
+        })
+        .await
+        .map_err(std::io::Error::other)??;
+        self.keyring.unlock(id, keys)?;
+        self.deliver(id, &source).await
+    }
     pub fn manifests(&self) -> &RwLock<BTreeMap<u64, RwLock<HairpinSource>>> {
         &self.manifests
     }
+    /// Notified whenever sources are registered or unregistered.
+    pub fn registrations(&self) -> &Notify {
+        &self.registered
+    }
+    pub fn events(&self) -> &SourceEvents {
+        &self.events
+    }
+    /// Cancelled once the daemon shuts down, ending long lived requests.
+    pub fn shutdown(&self) -> &CancellationToken {
+        &self.shutdown
+    }
     pub async fn new_id(&self) -> u64 {
-        self.counter
-            .fetch_add(1, std::sync::atomic::Ordering::SeqCst)
+        self.counter.fetch_add(1, Ordering::SeqCst)
+    }
+    /// Adds sources under new ids and delivers their values once they're registered, so delivery
+    /// neither blocks other requests nor leaves values of sources that failed to register behind.
+    pub async fn register(&self, sources: Vec<HairpinSource>) -> Result<Vec<u64>, Error> {
+        let mut ids = Vec::new();
+        {
+            let mut manifests = self.manifests.write().await;
+            let mut registered = Vec::new();
+            for source in sources {
+                registered.push((self.new_id().await, source));
+            }
+            if let Some(state) = &self.state {
+                state
+                    .insert(
+                        registered
+                            .iter()
+                            .filter(|(_, source)| source.origin().is_persistent())
+                            .map(|(id, source)| (*id, source.location())),
+                    )
+                    .await?;
+            }
+            for (id, source) in registered {
+                let change = SourceChange::Added {
+                    location: source.location().to_string(),
+                    origin: source.origin(),
+                };
+                // Ids are fresh, and watchers may look the source up as soon as they hear of it.
+                let source = manifests.entry(id).or_insert(RwLock::new(source));
+                self.events.send(id, source.get_mut().manifest(), change);
+                ids.push(id);
+            }
+        }
+        self.registered.notify_one();
+        let manifests = self.manifests.read().await;
+        for id in &ids {
+            if let Some(source) = manifests.get(id)
+                && let Err(err) = self.deliver(*id, &*source.read().await).await
+            {
+                log::warn!("Unable to deliver source {id}: {err}");
+            }
+        }
+        Ok(ids)
+    }
+    /// Drops sources and their delivered values, returning the sources that were registered.
+    pub async fn unregister(&self, ids: &[u64]) -> Result<Vec<(u64, HairpinSource)>, Error> {
+        let mut manifests = self.manifests.write().await;
+        if let Some(state) = &self.state {
+            state.remove(ids).await?;
+        }
+        let removed = ids
+            .iter()
+            .filter_map(|id| Some((*id, manifests.remove(id)?.into_inner())))
+            .collect::<Vec<_>>();
+        if let Some(delivery) = &self.delivery {
+            let mut delivered = Vec::new();
+            for (id, _) in &removed {
+                delivered.push(delivery.delivered(*id).await?);
+            }
+            delivery.revoke(ids).await?;
+            for ((id, source), names) in removed.iter().zip(delivered) {
+                for name in names {
+                    self.events.send(
+                        *id,
+                        source.manifest(),
+                        SourceChange::DeliveryRevoked {
+                            path: delivery.path(*id, &name),
+                            name,
+                        },
+                    );
+                }
+            }
+        }
+        self.keyring.lock(ids)?;
+        for (id, source) in &removed {
+            self.events.send(
+                *id,
+                source.manifest(),
+                SourceChange::Removed {
+                    location: source.location().to_string(),
+                },
+            );
+        }
+        self.registered.notify_one();
+        Ok(removed)
     }
 }
-#[derive(Debug, Default, Clone)]
+/// Daemon settings, read from a config file with the same names in kebab-case and overridden
+/// by command line flags.
+#[derive(Debug, Default, Clone, Deserialize)]
 #[cfg_attr(feature = "cli", derive(clap::Args))]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
 pub struct HairpinDaemonOptions {
-    #[cfg_attr(feature = "cli", arg(long = "disable-mounting"))]
-    disable_mounting: bool,
+    /// Config file, `/etc/hairpin/hairpin.toml` when it exists
+    #[cfg_attr(feature = "cli", arg(long = "config"))]
+    #[serde(skip)]
+    config: Option<PathBuf>,
+    #[cfg_attr(feature = "cli", arg(long = "disable-mounting", num_args = 0..=1, default_missing_value = "true"))]
+    disable_mounting: Option<bool>,
+    /// Don't watch local source directories for changes to reload
+    #[cfg_attr(feature = "cli", arg(long = "disable-reload", num_args = 0..=1, default_missing_value = "true"))]
+    disable_reload: Option<bool>,
+    /// Filesystem whose sources are registered once mounted, as a device path or a `UUID=`,
+    /// `LABEL=`, `PARTUUID=` or `PARTLABEL=` tag; mounted filesystems are ignored without any
+    #[cfg_attr(feature = "cli", arg(long = "mount-source"))]
+    mount_sources: Vec<String>,
+    /// Filesystem type trusted mounts must have, any when not given
+    #[cfg_attr(feature = "cli", arg(long = "mount-fstype"))]
+    mount_fstypes: Vec<String>,
+    /// Only register sources of trusted mounts that are read only
+    #[cfg_attr(feature = "cli", arg(long = "mount-read-only", num_args = 0..=1, default_missing_value = "true"))]
+    mount_read_only: Option<bool>,
+    /// Watch the kernel mount table for filesystems carrying sources
+    #[cfg_attr(feature = "cli", arg(long = "watch-kernel", num_args = 0..=1, default_missing_value = "true"))]
+    watch_kernel: Option<bool>,
+    /// Watch userspace mount options (utab) for filesystems carrying sources
+    #[cfg_attr(feature = "cli", arg(long = "watch-userspace", num_args = 0..=1, default_missing_value = "true"))]
+    watch_userspace: Option<bool>,
+    /// Watch fstab and log filesystems added to or removed from it
+    #[cfg_attr(feature = "cli", arg(long = "watch-fstab", num_args = 0..=1, default_missing_value = "true"))]
+    watch_fstab: Option<bool>,
+    /// Deprecated and ignored, mount changes are read as they happen
+    #[cfg_attr(feature = "cli", arg(long = "poll-rate", hide = true))]
+    poll_rate: Option<u64>,
+    #[cfg_attr(feature = "cli", arg(long = "socket"))]
+    socket: Option<PathBuf>,
+    /// Most verbose messages logged, one of off, error, warn, info, debug or trace
+    #[cfg_attr(feature = "cli", arg(long = "log-level"))]
+    log_level: Option<LevelFilter>,
+    #[cfg_attr(feature = "cli", arg(long = "listen"))]
+    listen: Option<SocketAddr>,
+    #[cfg_attr(feature = "cli", arg(long = "allow-scheme"))]
+    allowed_schemes: Vec<String>,
+    /// Host remote item values may be fetched from, over an allowed scheme and with a digest
+    #[cfg_attr(feature = "cli", arg(long = "remote-value-host"))]
+    remote_value_hosts: Vec<String>,
+    /// Let sources read from disk fetch remote item values too
+    #[cfg_attr(feature = "cli", arg(long = "local-remote-values", num_args = 0..=1, default_missing_value = "true"))]
+    local_remote_values: Option<bool>,
+    #[cfg_attr(feature = "cli", arg(long = "state-dir"))]
+    state_dir: Option<PathBuf>,
+    /// Uid allowed to read item values besides root and the daemon's own user, without a policy file
+    #[cfg_attr(feature = "cli", arg(long = "reader-uid", conflicts_with = "policy"))]
+    reader_uids: Vec<u32>,
+    /// Policy file deciding which callers may manage sources and read items
+    #[cfg_attr(feature = "cli", arg(long = "policy"))]
+    policy: Option<PathBuf>,
+    #[cfg_attr(feature = "cli", arg(long = "age-identity"))]
+    age_identities: Vec<PathBuf>,
+    /// File of hex encoded Ed25519 publisher keys; when given, sources must be signed by one
+    #[cfg_attr(feature = "cli", arg(long = "trusted-key"))]
+    trusted_keys: Vec<PathBuf>,
+    /// File to append a JSON lines audit log of source changes, item accesses and denials to
+    #[cfg_attr(feature = "cli", arg(long = "audit-log"))]
+    audit_log: Option<PathBuf>,
+    /// Size in bytes past which the audit log is rotated
+    #[cfg_attr(feature = "cli", arg(long = "audit-max-size"))]
+    audit_max_size: Option<u64>,
+    /// Number of rotated audit logs to keep
+    #[cfg_attr(feature = "cli", arg(long = "audit-keep"))]
+    audit_keep: Option<usize>,
+    /// Chains audit log entries by HMAC so tampering can be detected with `hairpin audit verify`
+    #[cfg_attr(feature = "cli", arg(long = "audit-chain", num_args = 0..=1, default_missing_value = "true"))]
+    audit_chain: Option<bool>,
+    /// Key chaining the audit log, created if missing and best kept apart from the log,
+    /// `audit.key` in the state directory by default
+    #[cfg_attr(feature = "cli", arg(long = "audit-key"))]
+    audit_key: Option<PathBuf>,
+    /// Refuse to hand out item values whose access can't be written to the audit log
+    #[cfg_attr(feature = "cli", arg(long = "audit-fail-closed", num_args = 0..=1, default_missing_value = "true"))]
+    audit_fail_closed: Option<bool>,
+    #[cfg_attr(feature = "cli", arg(long = "delivery-target"))]
+    delivery_target: Option<PathBuf>,
+    #[cfg_attr(feature = "cli", arg(long = "delivery-size"))]
+    delivery_size: Option<String>,
+    /// Timeout in seconds for fetching remote sources
+    #[cfg_attr(feature = "cli", arg(long = "http-timeout"))]
+    http_timeout: Option<u64>,
+    /// Largest remote manifest or item value in bytes
+    #[cfg_attr(feature = "cli", arg(long = "http-max-size"))]
+    http_max_size: Option<u64>,
+    /// PEM bundle trusted instead of the system roots for https sources
+    #[cfg_attr(feature = "cli", arg(long = "http-ca-bundle"))]
+    http_ca_bundle: Option<PathBuf>,
+}
+impl HairpinDaemonOptions {
+    pub const DEFAULT_SOCKET: &'static str = "/run/hairpin/hairpin.sock";
+    pub const DEFAULT_SCHEMES: &'static [&'static str] = &["file"];
+    pub const DEFAULT_STATE_DIR: &'static str = "/var/lib/hairpin";
+    pub const DEFAULT_DELIVERY_SIZE: &'static str = "16m";
+    pub const DEFAULT_AUDIT_KEEP: usize = 5;
+    pub const DEFAULT_AUDIT_KEY: &'static str = "audit.key";
+    pub const DEFAULT_CONFIG: &'static str = "/etc/hairpin/hairpin.toml";
+    /// The config file given on the command line, if any.
+    pub fn config(&self) -> Option<&Path> {
+        self.config.as_deref()
+    }
+    /// Fills in whatever these options leave unset from `base`, typically the config file.
+    pub fn over(self, base: Self) -> Self {
+        fn or<T>(value: Vec<T>, base: Vec<T>) -> Vec<T> {
+            if value.is_empty() { base } else { value }
+        }
+        Self {
+            config: self.config.or(base.config),
+            disable_mounting: self.disable_mounting.or(base.disable_mounting),
+            disable_reload: self.disable_reload.or(base.disable_reload),
+            mount_sources: or(self.mount_sources, base.mount_sources),
+            mount_fstypes: or(self.mount_fstypes, base.mount_fstypes),
+            mount_read_only: self.mount_read_only.or(base.mount_read_only),
+            watch_kernel: self.watch_kernel.or(base.watch_kernel),
+            watch_userspace: self.watch_userspace.or(base.watch_userspace),
+            watch_fstab: self.watch_fstab.or(base.watch_fstab),
+            poll_rate: self.poll_rate.or(base.poll_rate),
+            socket: self.socket.or(base.socket),
+            log_level: self.log_level.or(base.log_level),
+            listen: self.listen.or(base.listen),
+            allowed_schemes: or(self.allowed_schemes, base.allowed_schemes),
+            remote_value_hosts: or(self.remote_value_hosts, base.remote_value_hosts),
+            local_remote_values: self.local_remote_values.or(base.local_remote_values),
+            state_dir: self.state_dir.or(base.state_dir),
+            reader_uids: or(self.reader_uids, base.reader_uids),
+            policy: self.policy.or(base.policy),
+            age_identities: or(self.age_identities, base.age_identities),
+            trusted_keys: or(self.trusted_keys, base.trusted_keys),
+            audit_log: self.audit_log.or(base.audit_log),
+            audit_max_size: self.audit_max_size.or(base.audit_max_size),
+            audit_keep: self.audit_keep.or(base.audit_keep),
+            audit_chain: self.audit_chain.or(base.audit_chain),
+            audit_key: self.audit_key.or(base.audit_key),
+            audit_fail_closed: self.audit_fail_closed.or(base.audit_fail_closed),
+            delivery_target: self.delivery_target.or(base.delivery_target),
+            delivery_size: self.delivery_size.or(base.delivery_size),
+            http_timeout: self.http_timeout.or(base.http_timeout),
+            http_max_size: self.http_max_size.or(base.http_max_size),
+            http_ca_bundle: self.http_ca_bundle.or(base.http_ca_bundle),
+        }
+    }
+    pub fn socket(&self) -> &Path {
+        self.socket
+            .as_deref()
+            .unwrap_or(Path::new(Self::DEFAULT_SOCKET))
+    }
+    pub fn state_dir(&self) -> &Path {
+        self.state_dir
+            .as_deref()
+            .unwrap_or(Path::new(Self::DEFAULT_STATE_DIR))
+    }
+    pub fn age_identities(&self) -> &[PathBuf] {
+        &self.age_identities
+    }
+    pub fn reader_uids(&self) -> &[u32] {
+        &self.reader_uids
+    }
+    pub fn policy(&self) -> Option<&Path> {
+        self.policy.as_deref()
+    }
+    pub fn trusted_keys(&self) -> &[PathBuf] {
+        &self.trusted_keys
+    }
+    pub fn audit_log(&self) -> Option<&Path> {
+        self.audit_log.as_deref()
+    }
+    pub fn audit_max_size(&self) -> Option<u64> {
+        self.audit_max_size
+    }
+    pub fn audit_keep(&self) -> usize {
+        self.audit_keep.unwrap_or(Self::DEFAULT_AUDIT_KEEP)
+    }
+    pub fn audit_chain(&self) -> bool {
+        self.audit_chain.unwrap_or(false)
+    }
+    pub fn audit_key(&self) -> PathBuf {
+        self.audit_key
+            .clone()
+            .unwrap_or_else(|| self.state_dir().join(Self::DEFAULT_AUDIT_KEY))
+    }
+    pub fn audit_fail_closed(&self) -> bool {
+        self.audit_fail_closed.unwrap_or(false)
+    }
+    pub fn delivery_target(&self) -> Option<&Path> {
+        self.delivery_target.as_deref()
+    }
+    pub fn http(&self) -> HttpOptions {
+        let mut options = HttpOptions::default();
+        if let Some(timeout) = self.http_timeout {
+            options = options.with_timeout(Duration::from_secs(timeout));
+        }
+        if let Some(max_size) = self.http_max_size {
+            options = options.with_max_size(max_size);
+        }
+        if let Some(ca_bundle) = &self.http_ca_bundle {
+            options = options.with_ca_bundle(ca_bundle.clone());
+        }
+        options
+    }
+    pub fn delivery_size(&self) -> &str {
+        self.delivery_size
+            .as_deref()
+            .unwrap_or(Self::DEFAULT_DELIVERY_SIZE)
+    }
+    pub fn disable_mounting(&self) -> bool {
+        self.disable_mounting.unwrap_or(false)
+    }
+    pub(crate) fn mount_trust(&self) -> Result<MountTrust, Error> {
+        Ok(MountTrust::new(&self.mount_sources)?
+            .with_fstypes(&self.mount_fstypes)
+            .with_read_only(self.mount_read_only.unwrap_or(false)))
+    }
+    pub fn disable_reload(&self) -> bool {
+        self.disable_reload.unwrap_or(false)
+    }
+    pub fn watch_kernel(&self) -> bool {
+        self.watch_kernel.unwrap_or(true)
+    }
+    pub fn watch_userspace(&self) -> bool {
+        self.watch_userspace.unwrap_or(true)
+    }
+    pub fn watch_fstab(&self) -> bool {
+        self.watch_fstab.unwrap_or(false)
+    }
+    /// The deprecated poll rate, only kept so older configs still load.
+    pub fn poll_rate(&self) -> Option<u64> {
+        self.poll_rate
+    }
+    pub fn log_level(&self) -> LevelFilter {
+        self.log_level.unwrap_or(LevelFilter::Info)
+    }
+    pub fn listen(&self) -> Option<SocketAddr> {
+        self.listen
+    }
+    /// Which remote item values sources may fetch, none unless hosts are allowed.
+    pub fn remote_policy(&self) -> RemotePolicy {
+        RemotePolicy::new(
+            self.allowed_schemes(),
+            self.remote_value_hosts.iter().cloned(),
+        )
+        .with_local(self.local_remote_values.unwrap_or(false))
+    }
+    pub fn allowed_schemes(&self) -> Vec<String> {
+        if self.allowed_schemes.is_empty() {
+            Self::DEFAULT_SCHEMES
+                .iter()
+                .map(|scheme| scheme.to_string())
+                .collect()
+        } else {
+            self.allowed_schemes.clone()
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escapes_local_paths() {
+        let path = PathBuf::from("/srv/my secrets/50%#?");
+        let location = HairpinSourceLocation::Local(path.clone());
+        let uri = location.to_string();
+        assert_eq!(uri, "file://localhost/srv/my%20secrets/50%25%23%3F");
+        let parsed = HairpinSourceLocation::try_from(uri.parse::<Uri>().unwrap()).unwrap();
+        assert_eq!(parsed, HairpinSourceLocation::Local(path));
+    }
+    #[test]
+    fn escapes_non_utf8_paths() {
+        let path = PathBuf::from(OsStr::from_bytes(b"/srv/\xff"));
+        let uri = HairpinSourceLocation::Local(path.clone()).to_string();
+        assert_eq!(uri, "file://localhost/srv/%FF");
+        let parsed = HairpinSourceLocation::try_from(uri.parse::<Uri>().unwrap()).unwrap();
+        assert_eq!(parsed, HairpinSourceLocation::Local(path));
+    }
 }
@@ -0,0 +1,364 @@
+use std::{
+    os::unix::fs::{FileTypeExt, MetadataExt},
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::Arc,
+};
+
+use libmount::{
+    event::{MountEvent, MountEventMask, MountTable},
+    fs::FileSystemInfo,
+    serve::{MonitorServe, handler},
+};
+use manifest::Manifest;
+use tokio::sync::mpsc::{UnboundedSender, error::SendError};
+use tokio_util::sync::CancellationToken;
+
+use crate::{
+    Error,
+    audit::AuditEvent,
+    model::{
+        HairpinDaemon, HairpinDaemonOptions, HairpinSource, HairpinSourceLocation, SourceOrigin,
+    },
+};
+
+#[derive(Debug)]
+pub(crate) enum MountChange {
+    Mounted(Box<FileSystemInfo>),
+    Unmounted(PathBuf),
+}
+fn forward(
+    sender: &UnboundedSender<MountChange>,
+    event: MountEvent<'static>,
+) -> Result<(), SendError<MountChange>> {
+    let change = match event {
+        MountEvent::Mount {
+            filesystem,
+            table: MountTable::Kernel | MountTable::Userspace,
+        } => Some(MountChange::Mounted(filesystem)),
+        MountEvent::UMount {
+            filesystem,
+            table: MountTable::Kernel | MountTable::Userspace,
+        } => filesystem
+            .target()
+            .map(|target| MountChange::Unmounted(target.to_path_buf())),
+        MountEvent::Mount {
+            filesystem,
+            table: MountTable::Fstab,
+        } => {
+            log::info!("fstab now lists {}", describe(&filesystem));
+            None
+        }
+        MountEvent::UMount {
+            filesystem,
+            table: MountTable::Fstab,
+        } => {
+            log::info!("fstab no longer lists {}", describe(&filesystem));
+            None
+        }
+        _ => None,
+    };
+    match change {
+        Some(change) => sender.send(change),
+        None => Ok(()),
+    }
+}
+fn describe(filesystem: &FileSystemInfo) -> String {
+    format!(
+        "{} on {:?}",
+        filesystem.source().unwrap_or("none"),
+        filesystem.target().unwrap_or(Path::new("none"))
+    )
+}
+/// A filesystem trusted to carry sources, given by device path or tag.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum TrustedMount {
+    Tag(String, String),
+    Device(PathBuf),
+}
+impl FromStr for TrustedMount {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.split_once('=') {
+            Some((name @ ("UUID" | "LABEL" | "PARTUUID" | "PARTLABEL"), tag))
+                if !tag.is_empty() =>
+            {
+                Ok(TrustedMount::Tag(name.to_string(), tag.to_string()))
+            }
+            None if value.starts_with('/') => Ok(TrustedMount::Device(value.into())),
+            _ => Err(Error::InvalidOption(format!(
+                "{value:?} isn't a device path or a UUID=, LABEL=, PARTUUID= or PARTLABEL= tag"
+            ))),
+        }
+    }
+}
+impl TrustedMount {
+    /// Whether a filesystem was mounted by this tag or from this device. Kernel mounts name
+    /// their device rather than the tag, which is then looked up in `/dev/disk`.
+    async fn matches(&self, filesystem: &FileSystemInfo) -> bool {
+        match self {
+            TrustedMount::Tag(name, value) => {
+                filesystem
+                    .tag()
+                    .is_some_and(|tag| tag.name() == name && tag.value() == value)
+                    || is_device(&tag_path(name, value), filesystem).await
+            }
+            TrustedMount::Device(path) => is_device(path, filesystem).await,
+        }
+    }
+}
+/// The udev symlink of a tag, such as `/dev/disk/by-label/SECRETS`.
+fn tag_path(name: &str, value: &str) -> PathBuf {
+    // udev escapes anything that isn't safe in a file name, as in `My\x20Key`.
+    let mut escaped = String::new();
+    for char in value.chars() {
+        match char {
+            char if char.is_ascii_alphanumeric() || "#+-.:=@_".contains(char) => escaped.push(char),
+            char if !char.is_ascii() => escaped.push(char),
+            char => escaped.push_str(&format!("\\x{:02x}", char as u32)),
+        }
+    }
+    Path::new("/dev/disk")
+        .join(format!("by-{}", name.to_ascii_lowercase()))
+        .join(escaped)
+}
+/// Whether a filesystem was mounted from the device at `path`, by path or device number.
+async fn is_device(path: &Path, filesystem: &FileSystemInfo) -> bool {
+    let Ok(device) = tokio::fs::canonicalize(path).await else {
+        return false;
+    };
+    if let Some(source) = filesystem.source()
+        && tokio::fs::canonicalize(source)
+            .await
+            .is_ok_and(|source| source == device)
+    {
+        return true;
+    }
+    match (tokio::fs::metadata(&device).await, filesystem.devno()) {
+        (Ok(metadata), Some(devno)) => {
+            metadata.file_type().is_block_device() && metadata.rdev() == devno
+        }
+        _ => false,
+    }
+}
+/// Mounted filesystems whose sources get registered, none unless configured.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct MountTrust {
+    mounts: Vec<TrustedMount>,
+    fstypes: Vec<String>,
+    read_only: bool,
+}
+impl MountTrust {
+    pub(crate) fn new(mounts: &[String]) -> Result<Self, Error> {
+        Ok(Self {
+            mounts: mounts
+                .iter()
+                .map(|mount| mount.parse())
+                .collect::<Result<_, _>>()?,
+            ..Default::default()
+        })
+    }
+    /// Filesystem types trusted mounts must have, any when empty.
+    pub(crate) fn with_fstypes(mut self, fstypes: &[String]) -> Self {
+        self.fstypes = fstypes.to_vec();
+        self
+    }
+    /// Requires trusted mounts to be read only.
+    pub(crate) fn with_read_only(mut self, read_only: bool) -> Self {
+        self.read_only = read_only;
+        self
+    }
+    pub(crate) fn is_empty(&self) -> bool {
+        self.mounts.is_empty()
+    }
+    pub(crate) async fn allows(&self, filesystem: &FileSystemInfo) -> bool {
+        if self.read_only && !filesystem.read_only() {
+            return false;
+        }
+        if !self.fstypes.is_empty()
+            && !filesystem
+                .fstype()
+                .is_some_and(|fstype| self.fstypes.iter().any(|trusted| trusted == fstype))
+        {
+            return false;
+        }
+        for mount in &self.mounts {
+            if mount.matches(filesystem).await {
+                return true;
+            }
+        }
+        false
+    }
+}
+/// Registers sources found at the root of newly mounted trusted filesystems, including those
+/// already mounted at startup, and drops them again once the filesystem is unmounted.
+pub(crate) async fn watch_mounts(
+    daemon: &Arc<HairpinDaemon>,
+    options: &HairpinDaemonOptions,
+    shutdown: CancellationToken,
+) -> Result<(), Error> {
+    let trust = options.mount_trust()?;
+    if options.disable_mounting() || trust.is_empty() {
+        return Ok(());
+    }
+    let (sender, mut changes) = tokio::sync::mpsc::unbounded_channel();
+    let builder = MonitorServe::builder()
+        .with_kernel(options.watch_kernel())
+        .with_userspace(options.watch_userspace(), None)
+        .with_fstab(options.watch_fstab(), None)
+        .with_handler(
+            MountEventMask::MOUNT | MountEventMask::UMOUNT,
+            handler(move |event| forward(&sender, event)),
+        );
+    let (serve, close, mut errors) = builder
+        .build()
+        .map_err(|err| Error::MountMonitor(err.to_string()))?;
+    let monitor = tokio::spawn(serve);
+    loop {
+        tokio::select! {
+            Some(change) = changes.recv() => {
+                if let Err(err) = apply(daemon, &trust, change).await {
+                    log::warn!("Unable to apply mount change: {err}");
+                }
+            }
+            Some(err) = errors.recv() => log::error!("Mount monitor error: {err}"),
+            _ = shutdown.cancelled() => break,
+        }
+    }
+    let _ = close.send(());
+    match monitor.await {
+        Ok(Ok(())) => Ok(()),
+        Ok(Err(err)) => Err(Error::MountMonitor(err.to_string())),
+        Err(err) => Err(Error::MountMonitor(err.to_string())),
+    }
+}
+async fn apply(
+    daemon: &HairpinDaemon,
+    trust: &MountTrust,
+    change: MountChange,
+) -> Result<(), Error> {
+    match change {
+        MountChange::Mounted(filesystem) => {
+            if !trust.allows(&filesystem).await {
+                return Ok(());
+            }
+            let Some(target) = filesystem.target().map(Path::to_path_buf) else {
+                return Ok(());
+            };
+            if !tokio::fs::try_exists(target.join(Manifest::NAME)).await? {
+                return Ok(());
+            }
+            if !mounted_sources(daemon, &target).await.is_empty() {
+                return Ok(());
+            }
+            let location = HairpinSourceLocation::Local(target);
+            let manifest = daemon.resolve(&location).await?;
+            let ids = daemon
+                .register(vec![
+                    HairpinSource::new(location.clone(), manifest)
+                        .with_origin(SourceOrigin::ExternalDisk),
+                ])
+                .await?;
+            for id in ids {
+                daemon
+                    .record(AuditEvent::SourceMounted {
+                        source: id,
+                        location: location.to_string(),
+                        filesystem: (*filesystem).clone(),
+                    })
+                    .await;
+            }
+        }
+        MountChange::Unmounted(target) => {
+            let ids = mounted_sources(daemon, &target).await;
+            if ids.is_empty() {
+                return Ok(());
+            }
+            for (id, source) in daemon.unregister(&ids).await? {
+                daemon
+                    .record(AuditEvent::SourceUnmounted {
+                        source: id,
+                        location: source.location().to_string(),
+                    })
+                    .await;
+            }
+        }
+    }
+    Ok(())
+}
+async fn mounted_sources(daemon: &HairpinDaemon, target: &Path) -> Vec<u64> {
+    let mut ids = Vec::new();
+    for (id, source) in daemon.manifests().read().await.iter() {
+        let source = source.read().await;
+        if source.origin() == SourceOrigin::ExternalDisk
+            && matches!(source.location(), HairpinSourceLocation::Local(path) if path == target)
+        {
+            ids.push(*id);
+        }
+    }
+    ids
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filesystem(info: serde_json::Value) -> FileSystemInfo {
+        serde_json::from_value(info).unwrap()
+    }
+    #[test]
+    fn parses_trusted_mounts() {
+        assert_eq!(
+            "LABEL=SECRETS".parse::<TrustedMount>().unwrap(),
+            TrustedMount::Tag("LABEL".to_string(), "SECRETS".to_string())
+        );
+        assert_eq!(
+            "/dev/sdb1".parse::<TrustedMount>().unwrap(),
+            TrustedMount::Device("/dev/sdb1".into())
+        );
+        for invalid in ["sdb1", "UUID=", "FOO=bar"] {
+            assert!(invalid.parse::<TrustedMount>().is_err(), "{invalid}");
+        }
+    }
+    #[tokio::test]
+    async fn trusts_only_configured_mounts() {
+        let usb = filesystem(serde_json::json!({
+            "source": "/dev/null",
+            "target": "/media/usb",
+            "fstype": "vfat",
+            "tag": { "name": "LABEL", "value": "SECRETS" },
+        }));
+        let root = filesystem(serde_json::json!({
+            "source": "/dev/zero",
+            "target": "/",
+            "fstype": "ext4",
+        }));
+        assert!(!MountTrust::default().allows(&usb).await);
+        for trusted in ["/dev/null", "LABEL=SECRETS"] {
+            let trust = MountTrust::new(&[trusted.to_string()]).unwrap();
+            assert!(trust.allows(&usb).await, "{trusted}");
+            assert!(!trust.allows(&root).await, "{trusted}");
+        }
+        let trust = MountTrust::new(&["/dev/null".to_string()]).unwrap();
+        let fstypes = trust.clone().with_fstypes(&["ext4".to_string()]);
+        assert!(!fstypes.allows(&usb).await);
+        assert!(!trust.clone().with_read_only(true).allows(&usb).await);
+        let read_only = filesystem(serde_json::json!({
+            "source": "/dev/null",
+            "target": "/media/usb",
+            "vfs_options": "ro,nosuid",
+        }));
+        assert!(trust.with_read_only(true).allows(&read_only).await);
+    }
+    #[test]
+    fn escapes_tags_like_udev() {
+        assert_eq!(
+            tag_path("LABEL", "My Key/2"),
+            Path::new("/dev/disk/by-label/My\\x20Key\\x2f2")
+        );
+        assert_eq!(
+            tag_path("UUID", "1234-ABCD"),
+            Path::new("/dev/disk/by-uuid/1234-ABCD")
+        );
+    }
+}
@@ -0,0 +1,182 @@
+use std::{fmt::Display, path::PathBuf};
+
+use serde::Serialize;
+use tonic::{Request, Status, service::Interceptor, transport::server::UdsConnectInfo};
+
+/// The process behind a request, known only for callers on the Unix socket.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Caller {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    uid: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    gid: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pid: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    unit: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    exe: Option<PathBuf>,
+}
+impl Caller {
+    pub fn new(uid: u32, gid: u32, pid: Option<i32>) -> Self {
+        Self {
+            uid: Some(uid),
+            gid: Some(gid),
+            pid,
+            unit: None,
+            exe: None,
+        }
+    }
+    /// A hypothetical caller, as described to the explain RPC.
+    pub fn subject(
+        uid: Option<u32>,
+        gid: Option<u32>,
+        unit: Option<String>,
+        exe: Option<PathBuf>,
+    ) -> Self {
+        Self {
+            uid,
+            gid,
+            pid: None,
+            unit,
+            exe,
+        }
+    }
+    /// The caller a request was tagged with, or an unknown caller. Its unit and executable are
+    /// read from `/proc` here, through the blocking pool, rather than in the interceptor.
+    pub async fn of<T>(request: &Request<T>) -> Self {
+        let mut caller = request
+            .extensions()
+            .get::<Self>()
+            .cloned()
+            .unwrap_or_default();
+        if let Some(pid) = caller.pid {
+            caller.unit = unit_of(pid).await;
+            caller.exe = tokio::fs::read_link(format!("/proc/{pid}/exe")).await.ok();
+        }
+        caller
+    }
+    pub fn uid(&self) -> Option<u32> {
+        self.uid
+    }
+    pub fn gid(&self) -> Option<u32> {
+        self.gid
+    }
+    pub fn pid(&self) -> Option<i32> {
+        self.pid
+    }
+    /// The systemd unit the caller runs in, from its cgroup.
+    pub fn unit(&self) -> Option<&str> {
+        self.unit.as_deref()
+    }
+    pub fn exe(&self) -> Option<&PathBuf> {
+        self.exe.as_ref()
+    }
+}
+impl Display for Caller {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut subject = Vec::new();
+        if let Some(uid) = self.uid {
+            subject.push(format!("uid {uid}"));
+        }
+        if let Some(unit) = &self.unit {
+            subject.push(format!("unit {unit}"));
+        }
+        let mut process = Vec::new();
+        if let Some(pid) = self.pid {
+            process.push(format!("pid {pid}"));
+        }
+        if let Some(exe) = &self.exe {
+            process.push(exe.display().to_string());
+        }
+        match (subject.is_empty(), process.is_empty()) {
+            (true, true) => f.write_str("unknown caller"),
+            (true, false) => f.write_str(&process.join(", ")),
+            (false, true) => f.write_str(&subject.join(" ")),
+            (false, false) => write!(f, "{} ({})", subject.join(" "), process.join(", ")),
+        }
+    }
+}
+/// The system service or scope in the process's unified or systemd cgroup path. Units of user
+/// managers are left out as any user may name them, and so are cgroups a service creates below
+/// its own.
+async fn unit_of(pid: i32) -> Option<String> {
+    let cgroups = tokio::fs::read_to_string(format!("/proc/{pid}/cgroup"))
+        .await
+        .ok()?;
+    cgroups
+        .lines()
+        .filter_map(|line| {
+            line.strip_prefix("0::")
+                .or_else(|| line.split_once(":name=systemd:").map(|(_, path)| path))
+        })
+        .find_map(system_unit)
+        .map(str::to_string)
+}
+/// The outermost unit of a cgroup path under `system.slice`, below any nested slices.
+fn system_unit(path: &str) -> Option<&str> {
+    let mut names = path.trim_start_matches('/').split('/');
+    if names.next()? != "system.slice" {
+        return None;
+    }
+    names
+        .find(|name| !name.ends_with(".slice"))
+        .filter(|name| name.ends_with(".service") || name.ends_with(".scope"))
+}
+/// Tags every request with the peer credentials of its Unix socket connection, without any I/O.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CallerTag;
+impl Interceptor for CallerTag {
+    fn call(&mut self, mut request: Request<()>) -> Result<Request<()>, Status> {
+        let caller = request
+            .extensions()
+            .get::<UdsConnectInfo>()
+            .and_then(|info| info.peer_cred)
+            .map(|credentials| Caller::new(credentials.uid(), credentials.gid(), credentials.pid()))
+            .unwrap_or_default();
+        request.extensions_mut().insert(caller);
+        Ok(request)
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn units_come_from_the_system_slice() {
+        assert_eq!(
+            system_unit("/system.slice/postgresql.service"),
+            Some("postgresql.service")
+        );
+        assert_eq!(
+            system_unit("/system.slice/system-getty.slice/getty@tty1.service"),
+            Some("getty@tty1.service")
+        );
+        assert_eq!(
+            system_unit("/system.slice/app.service/postgresql.service"),
+            Some("app.service")
+        );
+    }
+    #[tokio::test]
+    async fn processes_are_resolved_by_handlers() {
+        let mut request = Request::new(());
+        request
+            .extensions_mut()
+            .insert(Caller::new(0, 0, Some(std::process::id() as i32)));
+        assert_eq!(request.extensions().get::<Caller>().unwrap().exe(), None);
+        let caller = Caller::of(&request).await;
+        assert_eq!(caller.exe(), std::env::current_exe().ok().as_ref());
+        assert_eq!(Caller::of(&Request::new(())).await, Caller::default());
+    }
+    #[test]
+    fn user_units_are_ignored() {
+        assert_eq!(
+            system_unit(
+                "/user.slice/user-1000.slice/user@1000.service/app.slice/postgresql.service"
+            ),
+            None
+        );
+        assert_eq!(system_unit("/postgresql.service"), None);
+        assert_eq!(system_unit("/system.slice"), None);
+    }
+}
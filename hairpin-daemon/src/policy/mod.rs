@@ -0,0 +1,6 @@
+mod caller;
+mod policy;
+mod selector;
+pub use caller::*;
+pub use policy::*;
+pub use selector::*;
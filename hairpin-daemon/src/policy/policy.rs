@@ -0,0 +1,410 @@
+use std::{
+    fmt::Display,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use manifest::{Item, Manifest};
+use serde::{Deserialize, Serialize};
+
+use crate::Error;
+
+use super::{Caller, LabelSelector, PropertySelectors, matches_properties};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Action {
+    Create,
+    Delete,
+    List,
+    Unlock,
+    Read,
+    Explain,
+}
+impl Action {
+    pub const ALL: &'static [Action] = &[
+        Action::Create,
+        Action::Delete,
+        Action::List,
+        Action::Unlock,
+        Action::Read,
+        Action::Explain,
+    ];
+}
+impl Display for Action {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Action::Create => f.write_str("create"),
+            Action::Delete => f.write_str("delete"),
+            Action::List => f.write_str("list"),
+            Action::Unlock => f.write_str("unlock"),
+            Action::Read => f.write_str("read"),
+            Action::Explain => f.write_str("explain"),
+        }
+    }
+}
+impl FromStr for Action {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .iter()
+            .find(|action| action.to_string() == s)
+            .copied()
+            .ok_or(Error::InvalidRequest("unknown action"))
+    }
+}
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Effect {
+    #[default]
+    Allow,
+    Deny,
+}
+impl Display for Effect {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Effect::Allow => f.write_str("allow"),
+            Effect::Deny => f.write_str("deny"),
+        }
+    }
+}
+/// Grants callers actions on sources and items. A matching deny rule always wins, and anything
+/// no rule allows is denied.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Policy {
+    #[serde(default, rename = "rule")]
+    rules: Vec<Rule>,
+}
+/// Empty subject or target constraints match anything; item constraints only match reads.
+/// Callers without credentials, such as TCP peers, only match rules with `anonymous = true`.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Rule {
+    name: Option<String>,
+    #[serde(default)]
+    effect: Effect,
+    actions: Vec<Action>,
+    #[serde(default)]
+    anonymous: bool,
+    #[serde(default)]
+    uid: Vec<u32>,
+    #[serde(default)]
+    gid: Vec<u32>,
+    #[serde(default)]
+    unit: Vec<String>,
+    #[serde(default)]
+    exe: Vec<PathBuf>,
+    #[serde(default)]
+    source_labels: LabelSelector,
+    #[serde(default)]
+    source_properties: PropertySelectors,
+    #[serde(default)]
+    item_labels: LabelSelector,
+    #[serde(default)]
+    item_properties: PropertySelectors,
+}
+/// The outcome of evaluating a policy, with the index of the deciding rule.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Decision {
+    effect: Effect,
+    rule: Option<usize>,
+}
+impl Decision {
+    pub fn effect(&self) -> Effect {
+        self.effect
+    }
+    /// None when no rule matched and the request is denied by default.
+    pub fn rule(&self) -> Option<usize> {
+        self.rule
+    }
+    pub fn is_allowed(&self) -> bool {
+        self.effect == Effect::Allow
+    }
+}
+impl Policy {
+    /// Used without a policy file: only root and the daemon's own user may manage sources, and
+    /// `readers` may also list them and read item values. Callers without credentials get nothing.
+    pub fn local(readers: impl IntoIterator<Item = u32>) -> Self {
+        let own = unsafe { libc::geteuid() };
+        Self {
+            rules: vec![
+                Rule {
+                    name: Some("manage sources".to_string()),
+                    actions: vec![Action::Create, Action::Delete, Action::List, Action::Unlock],
+                    uid: vec![0, own],
+                    ..Default::default()
+                },
+                Rule {
+                    name: Some("read items".to_string()),
+                    actions: vec![Action::List, Action::Read],
+                    uid: [0, own].into_iter().chain(readers).collect(),
+                    ..Default::default()
+                },
+                Rule {
+                    name: Some("explain".to_string()),
+                    actions: vec![Action::Explain],
+                    uid: vec![0, own],
+                    ..Default::default()
+                },
+            ],
+        }
+    }
+    pub async fn load(path: &Path) -> Result<Self, Error> {
+        toml::from_str(&tokio::fs::read_to_string(path).await?)
+            .map_err(|err| Error::InvalidPolicy(path.display().to_string(), err.to_string()))
+    }
+    pub fn rules(&self) -> &[Rule] {
+        &self.rules
+    }
+    pub fn evaluate(
+        &self,
+        caller: &Caller,
+        action: Action,
+        manifest: &Manifest,
+        item: Option<&Item>,
+    ) -> Decision {
+        let mut allowed = None;
+        for (index, rule) in self.rules.iter().enumerate() {
+            if !rule.matches(caller, action, manifest, item) {
+                continue;
+            }
+            match rule.effect {
+                Effect::Deny => {
+                    return Decision {
+                        effect: Effect::Deny,
+                        rule: Some(index),
+                    };
+                }
+                Effect::Allow => {
+                    allowed.get_or_insert(index);
+                }
+            }
+        }
+        Decision {
+            effect: if allowed.is_some() {
+                Effect::Allow
+            } else {
+                Effect::Deny
+            },
+            rule: allowed,
+        }
+    }
+    pub fn allows(
+        &self,
+        caller: &Caller,
+        action: Action,
+        manifest: &Manifest,
+        item: Option<&Item>,
+    ) -> bool {
+        self.evaluate(caller, action, manifest, item).is_allowed()
+    }
+    pub fn authorize(
+        &self,
+        caller: &Caller,
+        action: Action,
+        manifest: &Manifest,
+        item: Option<&Item>,
+    ) -> Result<(), Error> {
+        let decision = self.evaluate(caller, action, manifest, item);
+        if decision.is_allowed() {
+            return Ok(());
+        }
+        let target = match item {
+            Some(item) => format!("item {} of source {}", item.name(), manifest.name()),
+            None => format!("source {}", manifest.name()),
+        };
+        let reason = match decision.rule.map(|index| (index, &self.rules[index])) {
+            Some((index, rule)) => format!("denied by {}", rule.describe(index)),
+            None => "no rule allows it".to_string(),
+        };
+        Err(Error::Denied(format!(
+            "{caller} may not {action} {target}, {reason}"
+        )))
+    }
+}
+impl Rule {
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+    pub fn effect(&self) -> Effect {
+        self.effect
+    }
+    /// The rule's name, or its position in the policy file.
+    pub fn describe(&self, index: usize) -> String {
+        match &self.name {
+            Some(name) => format!("rule {name:?}"),
+            None => format!("rule {}", index + 1),
+        }
+    }
+    pub fn matches(
+        &self,
+        caller: &Caller,
+        action: Action,
+        manifest: &Manifest,
+        item: Option<&Item>,
+    ) -> bool {
+        self.actions.contains(&action)
+            && (self.anonymous || caller.uid().is_some())
+            && matches_any(&self.uid, caller.uid().as_ref())
+            && matches_any(&self.gid, caller.gid().as_ref())
+            && matches_any(&self.exe, caller.exe())
+            && (self.unit.is_empty()
+                || caller
+                    .unit()
+                    .is_some_and(|unit| self.unit.iter().any(|allowed| allowed == unit)))
+            && self.source_labels.matches(manifest.labels())
+            && matches_properties(manifest.properties(), &self.source_properties)
+            && match item {
+                Some(item) => {
+                    self.item_labels.matches(item.labels())
+                        && matches_properties(item.properties(), &self.item_properties)
+                }
+                None => self.item_labels.is_empty() && self.item_properties.is_empty(),
+            }
+    }
+}
+fn matches_any<T: PartialEq>(allowed: &[T], value: Option<&T>) -> bool {
+    allowed.is_empty() || value.is_some_and(|value| allowed.contains(value))
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manifest() -> Manifest {
+        toml::from_str(
+            r#"
+            id = "source"
+            name = "source"
+            version = "0.1.0"
+            labels = ["prod"]
+            [[items]]
+            id = "item"
+            name = "item"
+            encryption = "none"
+            labels = ["db"]
+            [items.properties]
+            port = 5432
+            [properties]
+            "#,
+        )
+        .unwrap()
+    }
+    fn user(uid: u32) -> Caller {
+        Caller::subject(Some(uid), Some(uid), None, None)
+    }
+    #[test]
+    fn local_policy_refuses_unknown_callers() {
+        let policy = Policy::local([1000]);
+        let manifest = manifest();
+        for action in Action::ALL {
+            assert!(!policy.allows(&Caller::default(), *action, &manifest, None));
+        }
+        assert!(policy.allows(&user(0), Action::Create, &manifest, None));
+        assert!(!policy.allows(&user(1000), Action::Create, &manifest, None));
+        assert!(policy.allows(&user(1000), Action::List, &manifest, None));
+        assert!(!policy.allows(&user(1001), Action::List, &manifest, None));
+    }
+    #[test]
+    fn unknown_callers_only_match_anonymous_rules() {
+        let policy: Policy = toml::from_str(
+            r#"
+            [[rule]]
+            actions = ["list"]
+            [[rule]]
+            actions = ["unlock"]
+            anonymous = true
+            "#,
+        )
+        .unwrap();
+        let manifest = manifest();
+        assert!(!policy.allows(&Caller::default(), Action::List, &manifest, None));
+        assert!(policy.allows(&user(1000), Action::List, &manifest, None));
+        assert!(policy.allows(&Caller::default(), Action::Unlock, &manifest, None));
+    }
+    fn policy(document: &str) -> Policy {
+        toml::from_str(document).unwrap()
+    }
+    #[test]
+    fn deny_rules_win_over_earlier_allows() {
+        let policy = policy(
+            r#"
+            [[rule]]
+            actions = ["read"]
+            [[rule]]
+            name = "no db"
+            effect = "deny"
+            actions = ["read"]
+            item-labels = ["db"]
+            "#,
+        );
+        let manifest = manifest();
+        let item = &manifest.items()[0];
+        let decision = policy.evaluate(&user(1000), Action::Read, &manifest, Some(item));
+        assert_eq!(decision.effect(), Effect::Deny);
+        assert_eq!(decision.rule(), Some(1));
+        assert!(policy.allows(&user(1000), Action::Read, &manifest, None));
+    }
+    #[test]
+    fn nothing_is_allowed_without_a_matching_rule() {
+        let policy = policy(
+            r#"
+            [[rule]]
+            actions = ["list"]
+            uid = [0]
+            "#,
+        );
+        let decision = policy.evaluate(&user(1000), Action::List, &manifest(), None);
+        assert_eq!(decision.effect(), Effect::Deny);
+        assert_eq!(decision.rule(), None);
+    }
+    #[test]
+    fn item_constraints_only_match_items() {
+        let policy = policy(
+            r#"
+            [[rule]]
+            actions = ["list", "read"]
+            item-properties = { port = { ge = 1024 } }
+            "#,
+        );
+        let manifest = manifest();
+        let item = &manifest.items()[0];
+        assert!(policy.allows(&user(1000), Action::Read, &manifest, Some(item)));
+        assert!(!policy.allows(&user(1000), Action::List, &manifest, None));
+    }
+    #[test]
+    fn source_selectors_narrow_rules() {
+        let manifest = manifest();
+        let labeled = policy(
+            r#"
+            [[rule]]
+            actions = ["list"]
+            source-labels = { any = ["prod", "staging"], none = ["legacy"] }
+            "#,
+        );
+        assert!(labeled.allows(&user(1000), Action::List, &manifest, None));
+        let unlabeled = policy(
+            r#"
+            [[rule]]
+            actions = ["list"]
+            source-labels = ["staging"]
+            "#,
+        );
+        assert!(!unlabeled.allows(&user(1000), Action::List, &manifest, None));
+    }
+    #[test]
+    fn mistyped_operators_fail_to_load() {
+        assert!(
+            toml::from_str::<Policy>(
+                r#"
+                [[rule]]
+                effect = "deny"
+                actions = ["read"]
+                item-properties = { port = { gte = 3 } }
+                "#,
+            )
+            .is_err()
+        );
+    }
+}
@@ -0,0 +1,258 @@
+use std::collections::BTreeMap;
+
+use serde::Deserialize;
+use toml::{Value, map::Map};
+
+use crate::service::proto::filter_property_map::entry::ValueType;
+
+/// Labels that must all, any or none be present. A plain list requires all of them, while a table
+/// must set at least one of `all`, `any` or `none` and nothing else.
+#[derive(Debug, Clone)]
+pub enum LabelSelector {
+    All(Vec<String>),
+    Sets {
+        all: Vec<String>,
+        any: Vec<String>,
+        none: Vec<String>,
+    },
+}
+impl<'de> Deserialize<'de> for LabelSelector {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        match Value::deserialize(deserializer)? {
+            Value::Table(sets) => {
+                let sets =
+                    LabelSets::deserialize(Value::Table(sets)).map_err(serde::de::Error::custom)?;
+                if sets.all.is_none() && sets.any.is_none() && sets.none.is_none() {
+                    return Err(serde::de::Error::custom(
+                        "label selector sets none of all, any or none",
+                    ));
+                }
+                Ok(LabelSelector::Sets {
+                    all: sets.all.unwrap_or_default(),
+                    any: sets.any.unwrap_or_default(),
+                    none: sets.none.unwrap_or_default(),
+                })
+            }
+            value => Vec::deserialize(value)
+                .map(LabelSelector::All)
+                .map_err(serde::de::Error::custom),
+        }
+    }
+}
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LabelSets {
+    all: Option<Vec<String>>,
+    any: Option<Vec<String>>,
+    none: Option<Vec<String>>,
+}
+impl Default for LabelSelector {
+    fn default() -> Self {
+        Self::All(Vec::new())
+    }
+}
+impl LabelSelector {
+    pub fn is_empty(&self) -> bool {
+        match self {
+            LabelSelector::All(all) => all.is_empty(),
+            LabelSelector::Sets { all, any, none } => {
+                all.is_empty() && any.is_empty() && none.is_empty()
+            }
+        }
+    }
+    pub fn matches(&self, labels: &[String]) -> bool {
+        match self {
+            LabelSelector::All(all) => all.iter().all(|label| labels.contains(label)),
+            LabelSelector::Sets { all, any, none } => {
+                all.iter().all(|label| labels.contains(label))
+                    && (any.is_empty() || any.iter().any(|label| labels.contains(label)))
+                    && !none.iter().any(|label| labels.contains(label))
+            }
+        }
+    }
+}
+/// Conditions on a single property. A plain value is compared for equality, while a table always
+/// holds operators, so unknown ones are rejected. Compare with a table through `eq`.
+#[derive(Debug, Clone)]
+pub enum PropertySelector {
+    Operators(PropertyOperators),
+    Eq(Value),
+}
+impl<'de> Deserialize<'de> for PropertySelector {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        match Value::deserialize(deserializer)? {
+            Value::Table(operators) => PropertyOperators::deserialize(Value::Table(operators))
+                .map(PropertySelector::Operators)
+                .map_err(serde::de::Error::custom),
+            value => Ok(PropertySelector::Eq(value)),
+        }
+    }
+}
+/// Every given operator must hold.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PropertyOperators {
+    eq: Option<Value>,
+    ne: Option<Value>,
+    #[serde(rename = "in")]
+    within: Option<Vec<Value>>,
+    exists: Option<bool>,
+    lt: Option<f64>,
+    le: Option<f64>,
+    gt: Option<f64>,
+    ge: Option<f64>,
+    #[serde(rename = "type", default, deserialize_with = "value_type")]
+    ty: Option<ValueType>,
+}
+impl PropertySelector {
+    pub fn matches(&self, value: Option<&Value>) -> bool {
+        match self {
+            PropertySelector::Eq(expected) => value == Some(expected),
+            PropertySelector::Operators(operators) => operators.matches(value),
+        }
+    }
+}
+impl PropertyOperators {
+    pub fn matches(&self, value: Option<&Value>) -> bool {
+        if let Some(exists) = self.exists
+            && exists != value.is_some()
+        {
+            return false;
+        }
+        let Some(value) = value else {
+            return self.eq.is_none()
+                && self.within.is_none()
+                && self.ty.is_none()
+                && self.compares().next().is_none();
+        };
+        let number = match value {
+            Value::Integer(value) => Some(*value as f64),
+            Value::Float(value) => Some(*value),
+            _ => None,
+        };
+        self.eq.as_ref().is_none_or(|eq| eq == value)
+            && self.ne.as_ref().is_none_or(|ne| ne != value)
+            && self
+                .within
+                .as_ref()
+                .is_none_or(|within| within.contains(value))
+            && self.ty.is_none_or(|ty| ty == ValueType::from(value))
+            && self.compares().all(|(compare, bound)| {
+                number.is_some_and(|number| match compare {
+                    Compare::Lt => number < bound,
+                    Compare::Le => number <= bound,
+                    Compare::Gt => number > bound,
+                    Compare::Ge => number >= bound,
+                })
+            })
+    }
+    fn compares(&self) -> impl Iterator<Item = (Compare, f64)> {
+        [
+            (Compare::Lt, self.lt),
+            (Compare::Le, self.le),
+            (Compare::Gt, self.gt),
+            (Compare::Ge, self.ge),
+        ]
+        .into_iter()
+        .filter_map(|(compare, bound)| bound.map(|bound| (compare, bound)))
+    }
+}
+#[derive(Debug, Clone, Copy)]
+enum Compare {
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+/// Selectors keyed by property name, all of which must match.
+pub type PropertySelectors = BTreeMap<String, PropertySelector>;
+pub fn matches_properties(properties: &Map<String, Value>, selectors: &PropertySelectors) -> bool {
+    selectors
+        .iter()
+        .all(|(key, selector)| selector.matches(properties.get(key)))
+}
+fn value_type<'de, D>(deserializer: D) -> Result<Option<ValueType>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let name = String::deserialize(deserializer)?;
+    ValueType::from_str_name(&name.to_ascii_uppercase())
+        .filter(|ty| *ty != ValueType::Undefined)
+        .map(Some)
+        .ok_or_else(|| serde::de::Error::custom(format!("unknown property type {name:?}")))
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn selector(document: &str) -> PropertySelector {
+        toml::from_str::<BTreeMap<String, PropertySelector>>(&format!("key = {document}"))
+            .unwrap()
+            .remove("key")
+            .unwrap()
+    }
+    #[test]
+    fn plain_values_compare_for_equality() {
+        let port = selector("5432");
+        assert!(port.matches(Some(&Value::Integer(5432))));
+        assert!(!port.matches(Some(&Value::Integer(5433))));
+        assert!(!port.matches(None));
+    }
+    #[test]
+    fn operators_must_all_hold() {
+        let port = selector("{ ge = 1024, lt = 49152, type = \"integer\" }");
+        assert!(port.matches(Some(&Value::Integer(5432))));
+        assert!(!port.matches(Some(&Value::Integer(80))));
+        assert!(!port.matches(Some(&Value::Float(5432.0))));
+        assert!(!port.matches(Some(&Value::String("5432".to_string()))));
+    }
+    #[test]
+    fn missing_properties_only_match_negative_operators() {
+        assert!(selector("{ exists = false }").matches(None));
+        assert!(selector("{ ne = \"prod\" }").matches(None));
+        assert!(!selector("{ in = [\"prod\"] }").matches(None));
+        assert!(!selector("{ gt = 0 }").matches(None));
+    }
+    #[test]
+    fn tables_are_compared_through_eq() {
+        let table = selector("{ eq = { a = 1 } }");
+        let mut value = Map::new();
+        value.insert("a".to_string(), Value::Integer(1));
+        assert!(table.matches(Some(&Value::Table(value))));
+    }
+    #[test]
+    fn unknown_operators_are_rejected() {
+        assert!(toml::from_str::<BTreeMap<String, PropertySelector>>("key = { gte = 3 }").is_err());
+        assert!(toml::from_str::<BTreeMap<String, PropertySelector>>("key = { a = 1 }").is_err());
+    }
+    #[test]
+    fn label_sets() {
+        let labels = ["prod".to_string(), "db".to_string()];
+        let selector: LabelSelector = toml::from_str::<BTreeMap<String, LabelSelector>>(
+            "key = { all = [\"db\"], none = [\"legacy\"] }",
+        )
+        .unwrap()
+        .remove("key")
+        .unwrap();
+        assert!(selector.matches(&labels));
+        assert!(!selector.matches(&["db".to_string(), "legacy".to_string()]));
+        assert!(LabelSelector::default().matches(&labels));
+    }
+    #[test]
+    fn misspelled_label_sets_are_rejected() {
+        let parse = |document: &str| {
+            toml::from_str::<BTreeMap<String, LabelSelector>>(&format!("key = {document}"))
+        };
+        assert!(parse("{ ayn = [\"db\"] }").is_err());
+        assert!(parse("{ any = [\"db\"], nome = [\"legacy\"] }").is_err());
+        assert!(parse("{}").is_err());
+        assert!(parse("{ any = [] }").is_ok());
+        assert!(parse("[\"db\"]").is_ok());
+    }
+}
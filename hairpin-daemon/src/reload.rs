@@ -0,0 +1,421 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+use inotify::{EventMask, Inotify, WatchDescriptor, WatchMask, Watches};
+use manifest::{Item, Manifest, ValueAccessor};
+use tokio::time::Instant;
+use tokio_stream::StreamExt;
+use tokio_util::sync::CancellationToken;
+
+use crate::{
+    Error,
+    audit::AuditEvent,
+    events::SourceChange,
+    model::{HairpinDaemon, HairpinDaemonOptions, HairpinSource, HairpinSourceLocation},
+};
+
+/// Digests of the raw values of a source's items by item id, `None` for unreadable values.
+/// Rotating a value on disk changes its digest even when the manifest stays the same.
+pub type ValueDigests = BTreeMap<String, Option<String>>;
+
+/// Item level difference between two versions of a source.
+#[derive(Debug, Clone, Default)]
+pub struct ItemDiff {
+    added: Vec<Item>,
+    removed: Vec<Item>,
+    changed: Vec<(Item, Item)>,
+}
+impl ItemDiff {
+    pub fn between(
+        old: &Manifest,
+        old_digests: &ValueDigests,
+        new: &Manifest,
+        new_digests: &ValueDigests,
+    ) -> Self {
+        let find = |manifest: &Manifest, id: &str| {
+            manifest
+                .items()
+                .iter()
+                .find(|item| item.id() == id)
+                .cloned()
+        };
+        let mut diff = Self::default();
+        for item in new.items() {
+            match find(old, item.id()) {
+                None => diff.added.push(item.clone()),
+                Some(previous)
+                    if previous != *item
+                        || old_digests.get(item.id()) != new_digests.get(item.id()) =>
+                {
+                    diff.changed.push((previous, item.clone()))
+                }
+                Some(_) => {}
+            }
+        }
+        diff.removed = old
+            .items()
+            .iter()
+            .filter(|item| find(new, item.id()).is_none())
+            .cloned()
+            .collect();
+        diff
+    }
+    pub fn added(&self) -> &[Item] {
+        &self.added
+    }
+    pub fn removed(&self) -> &[Item] {
+        &self.removed
+    }
+    /// Items present in both versions, as they were and as they are.
+    pub fn changed(&self) -> &[(Item, Item)] {
+        &self.changed
+    }
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+impl HairpinDaemon {
+    pub async fn value_digests(&self, source: &HairpinSource) -> ValueDigests {
+        let mut digests = ValueDigests::new();
+        for item in source.manifest().items() {
+            let digest = self
+                .read_item(source, item)
+                .await
+                .ok()
+                .map(|value| manifest::value::sha256(&value));
+            digests.insert(item.id().to_string(), digest);
+        }
+        digests
+    }
+    /// Re-resolves a source and swaps in its new manifest, updating delivered values of the
+    /// items that changed. `digests` are those of the current version and get replaced.
+    pub async fn reload(&self, id: u64, digests: &mut ValueDigests) -> Result<ItemDiff, Error> {
+        let manifests = self.manifests().read().await;
+        let entry = manifests.get(&id).ok_or(Error::UnknownSource(id))?;
+        let location = entry.read().await.location().clone();
+        let manifest = self.resolve(&location).await?;
+        let mut source = entry.write().await;
+        let replacement = HairpinSource::new(location, manifest).with_origin(source.origin());
+        let replacement_digests = self.value_digests(&replacement).await;
+        let diff = ItemDiff::between(
+            source.manifest(),
+            digests,
+            replacement.manifest(),
+            &replacement_digests,
+        );
+        let targets = source
+            .manifest()
+            .templates()
+            .iter()
+            .map(|template| template.target().to_string())
+            .collect::<Vec<_>>();
+        *source = replacement;
+        *digests = replacement_digests;
+        if !diff.is_empty() {
+            self.events().send(
+                id,
+                source.manifest(),
+                SourceChange::ManifestChanged(diff.clone()),
+            );
+        }
+        for target in targets {
+            if !source
+                .manifest()
+                .templates()
+                .iter()
+                .any(|template| template.target() == target)
+            {
+                self.revoke(id, source.manifest(), &target).await?;
+            }
+        }
+        // Templates may have changed on their own, and are only rewritten when their output does.
+        self.deliver_templates(id, &source).await?;
+        if diff.is_empty() {
+            return Ok(diff);
+        }
+        for item in diff.removed() {
+            self.revoke(id, source.manifest(), item.name()).await?;
+        }
+        for (previous, item) in diff.changed() {
+            if previous.name() != item.name() {
+                self.revoke(id, source.manifest(), previous.name()).await?;
+            }
+        }
+        self.deliver_items(
+            id,
+            &source,
+            diff.added()
+                .iter()
+                .chain(diff.changed().iter().map(|(_, item)| item)),
+        )
+        .await?;
+        self.record(AuditEvent::SourceReloaded {
+            source: id,
+            added: item_ids(diff.added()),
+            removed: item_ids(diff.removed()),
+            changed: item_ids(diff.changed().iter().map(|(_, item)| item)),
+        })
+        .await;
+        Ok(diff)
+    }
+}
+fn item_ids<'a>(items: impl IntoIterator<Item = &'a Item>) -> Vec<String> {
+    items
+        .into_iter()
+        .map(|item| item.id().to_string())
+        .collect()
+}
+/// Directories whose entries make up a local source: its root and those holding item values or
+/// templates.
+fn source_dirs(source: &HairpinSource) -> BTreeSet<PathBuf> {
+    let HairpinSourceLocation::Local(path) = source.location() else {
+        return BTreeSet::new();
+    };
+    let root = match path.is_dir() {
+        true => path.clone(),
+        false => path.parent().unwrap_or(Path::new("/")).to_path_buf(),
+    };
+    let mut dirs = BTreeSet::from([root.clone()]);
+    for item in source.manifest().items() {
+        if let ValueAccessor::Path(value) = item.value()
+            && let Some(parent) = root.join(value).parent()
+        {
+            dirs.insert(parent.to_path_buf());
+        }
+    }
+    for template in source.manifest().templates() {
+        if let Some(parent) = root.join(template.source()).parent() {
+            dirs.insert(parent.to_path_buf());
+        }
+    }
+    dirs
+}
+/// Inotify watches on the directories of every local source.
+#[derive(Debug, Default)]
+struct SourceWatches {
+    dirs: HashMap<PathBuf, WatchDescriptor>,
+    sources: HashMap<u64, (BTreeSet<PathBuf>, ValueDigests)>,
+}
+impl SourceWatches {
+    const MASK: WatchMask = WatchMask::CREATE
+        .union(WatchMask::DELETE)
+        .union(WatchMask::MODIFY)
+        .union(WatchMask::CLOSE_WRITE)
+        .union(WatchMask::MOVED_FROM)
+        .union(WatchMask::MOVED_TO);
+    /// Watches the directories of newly registered sources and drops those no longer needed.
+    /// New sources are watched before their values are digested, so no change goes unnoticed.
+    async fn sync(&mut self, daemon: &HairpinDaemon, watches: &mut Watches) {
+        let manifests = daemon.manifests().read().await;
+        let mut current = HashMap::new();
+        for (id, source) in manifests.iter() {
+            let source = source.read().await;
+            let dirs = source_dirs(&source);
+            if !dirs.is_empty() {
+                current.insert(*id, (dirs, source));
+            }
+        }
+        self.sources.retain(|id, _| current.contains_key(id));
+        let wanted = current
+            .values()
+            .flat_map(|(dirs, _)| dirs.iter().cloned())
+            .collect::<BTreeSet<_>>();
+        self.dirs.retain(|dir, wd| {
+            let keep = wanted.contains(dir);
+            if !keep {
+                let _ = watches.remove(wd.clone());
+            }
+            keep
+        });
+        for dir in wanted {
+            if !self.dirs.contains_key(&dir)
+                && let Ok(wd) = watches.add(&dir, Self::MASK)
+            {
+                self.dirs.insert(dir, wd);
+            }
+        }
+        for (id, (dirs, source)) in current {
+            match self.sources.get_mut(&id) {
+                Some(watched) => watched.0 = dirs,
+                None => {
+                    let digests = daemon.value_digests(&source).await;
+                    self.sources.insert(id, (dirs, digests));
+                }
+            }
+        }
+    }
+    /// Sources with a file in the watched directory.
+    fn affected(&self, wd: &WatchDescriptor) -> Vec<u64> {
+        let Some(dir) = self
+            .dirs
+            .iter()
+            .find_map(|(dir, watched)| (watched == wd).then_some(dir))
+        else {
+            return Vec::new();
+        };
+        self.sources
+            .iter()
+            .filter(|(_, (dirs, _))| dirs.contains(dir))
+            .map(|(id, _)| *id)
+            .collect()
+    }
+    /// Forgets a watch the kernel removed, e.g. because its directory was deleted.
+    fn forget(&mut self, wd: &WatchDescriptor) {
+        self.dirs.retain(|_, watched| watched != wd);
+    }
+}
+/// Reloads local sources when files in their directories change, waiting for writes to settle.
+/// Failing to watch only disables reloading, the daemon keeps serving.
+pub(crate) async fn watch_sources(
+    daemon: &Arc<HairpinDaemon>,
+    options: &HairpinDaemonOptions,
+    shutdown: CancellationToken,
+) -> Result<(), Error> {
+    if options.disable_reload() {
+        return Ok(());
+    }
+    if let Err(err) = reload_sources(daemon, shutdown).await {
+        log::warn!("Reloading is disabled, unable to watch sources: {err}");
+    }
+    Ok(())
+}
+async fn reload_sources(daemon: &HairpinDaemon, shutdown: CancellationToken) -> Result<(), Error> {
+    const SETTLE: Duration = Duration::from_millis(200);
+    /// Longest a steady stream of changes may hold reloads back.
+    const MAX_SETTLE: Duration = Duration::from_secs(2);
+    let inotify = Inotify::init()?;
+    let mut watches = inotify.watches();
+    let mut events = inotify.into_event_stream([0; 4096])?;
+    let mut watched = SourceWatches::default();
+    watched.sync(daemon, &mut watches).await;
+    loop {
+        let mut pending = BTreeSet::new();
+        tokio::select! {
+            _ = daemon.registrations().notified() => {}
+            Some(event) = events.next() => {
+                let deadline = Instant::now() + MAX_SETTLE;
+                let mut event = Some(event);
+                while let Some(next) = event {
+                    let next = next?;
+                    if next.mask.contains(EventMask::Q_OVERFLOW) {
+                        // The kernel dropped events, so any source may have changed.
+                        pending.extend(watched.sources.keys().copied());
+                    } else {
+                        pending.extend(watched.affected(&next.wd));
+                    }
+                    if next.mask.contains(EventMask::IGNORED) {
+                        watched.forget(&next.wd);
+                    }
+                    let settled = deadline.min(Instant::now() + SETTLE);
+                    event = tokio::select! {
+                        next = tokio::time::timeout_at(settled, events.next()) => next.ok().flatten(),
+                        _ = shutdown.cancelled() => return Ok(()),
+                    };
+                }
+            }
+            _ = shutdown.cancelled() => break,
+        }
+        for id in pending {
+            let Some((_, digests)) = watched.sources.get_mut(&id) else {
+                continue;
+            };
+            match daemon.reload(id, digests).await {
+                Ok(diff) if !diff.is_empty() => log::info!(
+                    "Reloaded source {id}: {} added, {} removed, {} changed",
+                    diff.added().len(),
+                    diff.removed().len(),
+                    diff.changed().len()
+                ),
+                Ok(_) => {}
+                Err(err) => log::warn!("Unable to reload source {id}: {err}"),
+            }
+        }
+        watched.sync(daemon, &mut watches).await;
+    }
+    Ok(())
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manifest(items: &str) -> Manifest {
+        toml::from_str(&format!(
+            r#"
+            id = "source"
+            name = "source"
+            version = "0.1.0"
+            labels = []
+            {items}
+            [properties]
+            "#
+        ))
+        .unwrap()
+    }
+    fn item(id: &str, name: &str) -> String {
+        format!(
+            r#"
+            [[items]]
+            id = "{id}"
+            name = "{name}"
+            value = "items/{id}"
+            encryption = "none"
+            labels = []
+            [items.properties]
+            "#
+        )
+    }
+    fn digests(values: &[(&str, &str)]) -> ValueDigests {
+        values
+            .iter()
+            .map(|(id, value)| (id.to_string(), Some(value.to_string())))
+            .collect()
+    }
+    fn ids(items: &[Item]) -> Vec<&str> {
+        items.iter().map(Item::id).collect()
+    }
+    #[test]
+    fn diffs_items_by_id() {
+        let old = manifest(&(item("a", "a") + &item("b", "b")));
+        let new = manifest(&(item("b", "b") + &item("c", "c")));
+        let diff = ItemDiff::between(
+            &old,
+            &digests(&[("a", "1"), ("b", "2")]),
+            &new,
+            &digests(&[("b", "2"), ("c", "3")]),
+        );
+        assert_eq!(ids(diff.added()), ["c"]);
+        assert_eq!(ids(diff.removed()), ["a"]);
+        assert!(diff.changed().is_empty());
+    }
+    #[test]
+    fn renamed_items_change() {
+        let old = manifest(&item("a", "a"));
+        let new = manifest(&item("a", "renamed"));
+        let digests = digests(&[("a", "1")]);
+        let diff = ItemDiff::between(&old, &digests, &new, &digests);
+        assert!(diff.added().is_empty() && diff.removed().is_empty());
+        let [(previous, item)] = diff.changed() else {
+            panic!("expected one changed item, got {diff:?}");
+        };
+        assert_eq!((previous.name(), item.name()), ("a", "renamed"));
+    }
+    #[test]
+    fn rotated_values_change() {
+        let manifest = manifest(&item("a", "a"));
+        let diff = ItemDiff::between(
+            &manifest,
+            &digests(&[("a", "1")]),
+            &manifest,
+            &digests(&[("a", "2")]),
+        );
+        assert_eq!(diff.changed().len(), 1);
+        let unreadable = ValueDigests::from([("a".to_string(), None)]);
+        assert!(ItemDiff::between(&manifest, &unreadable, &manifest, &unreadable).is_empty());
+        let diff = ItemDiff::between(&manifest, &digests(&[("a", "1")]), &manifest, &unreadable);
+        assert_eq!(diff.changed().len(), 1);
+    }
+}
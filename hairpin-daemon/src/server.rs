@@ -0,0 +1,146 @@
+use std::{io::ErrorKind, os::unix::fs::FileTypeExt, path::Path, sync::Arc};
+
+use tokio::{
+    net::{UnixListener, UnixStream},
+    signal::unix::{SignalKind, signal},
+};
+use tokio_stream::wrappers::UnixListenerStream;
+use tokio_util::sync::CancellationToken;
+use tonic::{
+    codegen::InterceptedService,
+    transport::{Server, server::Router},
+};
+
+use crate::{
+    Error,
+    model::{HairpinDaemon, HairpinDaemonOptions},
+    policy::CallerTag,
+    service::{
+        item::HairpinItemServiceServer,
+        policy::HairpinPolicyServiceServer,
+        source::{HairpinSourceServiceServer, Service, SourceSchemeGuard},
+    },
+};
+
+fn router(daemon: &Arc<HairpinDaemon>, options: &HairpinDaemonOptions) -> Router {
+    Server::builder()
+        .add_service(InterceptedService::new(
+            HairpinSourceServiceServer::with_interceptor(
+                Service::new(daemon.clone()),
+                SourceSchemeGuard::new(options.allowed_schemes()),
+            ),
+            CallerTag,
+        ))
+        .add_service(HairpinItemServiceServer::with_interceptor(
+            crate::service::item::Service::new(daemon.clone()),
+            CallerTag,
+        ))
+        .add_service(HairpinPolicyServiceServer::with_interceptor(
+            crate::service::policy::Service::new(daemon.clone()),
+            CallerTag,
+        ))
+}
+pub(crate) async fn shutdown_signal(shutdown: CancellationToken) -> Result<(), Error> {
+    let mut terminate = signal(SignalKind::terminate())?;
+    tokio::select! {
+        result = tokio::signal::ctrl_c() => result?,
+        _ = terminate.recv() => {},
+        _ = shutdown.cancelled() => {},
+    }
+    shutdown.cancel();
+    Ok(())
+}
+/// Removes a socket left behind by a daemon that is gone, refusing to replace one that still
+/// answers or a file that isn't a socket.
+async fn claim_socket(path: &Path) -> Result<(), Error> {
+    let metadata = match tokio::fs::symlink_metadata(path).await {
+        Ok(metadata) => metadata,
+        Err(error) if error.kind() == ErrorKind::NotFound => return Ok(()),
+        Err(error) => return Err(error.into()),
+    };
+    if !metadata.file_type().is_socket() || UnixStream::connect(path).await.is_ok() {
+        return Err(Error::SocketInUse(path.to_path_buf()));
+    }
+    tokio::fs::remove_file(path).await?;
+    Ok(())
+}
+pub(crate) async fn serve_unix(
+    daemon: &Arc<HairpinDaemon>,
+    options: &HairpinDaemonOptions,
+    shutdown: CancellationToken,
+) -> Result<(), Error> {
+    let path = options.socket();
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    claim_socket(path).await?;
+    let listener = UnixListener::bind(path)?;
+    let result = router(daemon, options)
+        .serve_with_incoming_shutdown(UnixListenerStream::new(listener), {
+            let shutdown = shutdown.clone();
+            async move { shutdown.cancelled().await }
+        })
+        .await;
+    shutdown.cancel();
+    let removed = tokio::fs::remove_file(path).await;
+    result?;
+    Ok(removed?)
+}
+pub(crate) async fn serve_tcp(
+    daemon: &Arc<HairpinDaemon>,
+    options: &HairpinDaemonOptions,
+    shutdown: CancellationToken,
+) -> Result<(), Error> {
+    if let Some(address) = options.listen() {
+        let result = router(daemon, options)
+            .serve_with_shutdown(address, {
+                let shutdown = shutdown.clone();
+                async move { shutdown.cancelled().await }
+            })
+            .await;
+        shutdown.cancel();
+        result?;
+    }
+    Ok(())
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dir(name: &str) -> std::path::PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("hairpin-server-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+    #[tokio::test]
+    async fn removes_stale_sockets() {
+        let path = dir("stale").join("hairpin.sock");
+        drop(std::os::unix::net::UnixListener::bind(&path).unwrap());
+        assert!(path.exists());
+        claim_socket(&path).await.unwrap();
+        assert!(!path.exists());
+        claim_socket(&path).await.unwrap();
+    }
+    #[tokio::test]
+    async fn keeps_live_sockets() {
+        let path = dir("live").join("hairpin.sock");
+        let _listener = UnixListener::bind(&path).unwrap();
+        assert!(matches!(
+            claim_socket(&path).await,
+            Err(Error::SocketInUse(_))
+        ));
+        assert!(path.exists());
+    }
+    #[tokio::test]
+    async fn keeps_other_files() {
+        let path = dir("file").join("hairpin.sock");
+        std::fs::write(&path, "").unwrap();
+        assert!(matches!(
+            claim_socket(&path).await,
+            Err(Error::SocketInUse(_))
+        ));
+        assert!(path.exists());
+    }
+}
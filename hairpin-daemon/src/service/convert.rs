@@ -0,0 +1,112 @@
+use manifest::Item;
+use toml::{Value, map::Map};
+
+use crate::{
+    events::{SourceChange, SourceEvent},
+    model::SourceOrigin,
+};
+
+use super::proto::{
+    DeliveryMaterialized, DeliveryRevoked, ItemRef, ManifestChanged, ManifestSource, PropertyArray,
+    PropertyObject, PropertyValue, SourceAdded, SourceRemoved, WatchSourceResponse,
+    property_object, property_value, watch_source_response,
+};
+
+impl From<&Value> for PropertyValue {
+    fn from(value: &Value) -> Self {
+        let value = match value {
+            Value::String(value) => property_value::Value::String(value.clone()),
+            Value::Integer(value) => property_value::Value::Integer(*value),
+            Value::Float(value) => property_value::Value::Float(*value),
+            Value::Boolean(value) => property_value::Value::Boolean(*value),
+            Value::Datetime(value) => property_value::Value::String(value.to_string()),
+            Value::Array(values) => property_value::Value::Array(PropertyArray {
+                value: values.iter().map(PropertyValue::from).collect(),
+            }),
+            Value::Table(values) => property_value::Value::Object(values.into()),
+        };
+        PropertyValue { value: Some(value) }
+    }
+}
+impl From<&Map<String, Value>> for PropertyObject {
+    fn from(value: &Map<String, Value>) -> Self {
+        PropertyObject {
+            items: value
+                .iter()
+                .map(|(name, value)| property_object::Item {
+                    name: name.clone(),
+                    value: Some(value.into()),
+                })
+                .collect(),
+        }
+    }
+}
+impl From<SourceOrigin> for ManifestSource {
+    fn from(value: SourceOrigin) -> Self {
+        match value {
+            SourceOrigin::Unknown => ManifestSource::Unknown,
+            SourceOrigin::InternalDisk => ManifestSource::InternalDisk,
+            SourceOrigin::ExternalDisk => ManifestSource::ExternalDisk,
+            SourceOrigin::Remote => ManifestSource::Remote,
+        }
+    }
+}
+impl From<&Item> for ItemRef {
+    fn from(value: &Item) -> Self {
+        ItemRef {
+            id: value.id().to_string(),
+            name: value.name().to_string(),
+        }
+    }
+}
+impl From<&SourceEvent> for WatchSourceResponse {
+    fn from(value: &SourceEvent) -> Self {
+        let manifest = value.manifest();
+        let event = match value.change() {
+            SourceChange::Added { location, origin } => {
+                watch_source_response::Event::SourceAdded(SourceAdded {
+                    location: location.clone(),
+                    source: ManifestSource::from(*origin) as i32,
+                })
+            }
+            SourceChange::Removed { location } => {
+                watch_source_response::Event::SourceRemoved(SourceRemoved {
+                    location: location.clone(),
+                })
+            }
+            SourceChange::ManifestChanged(diff) => {
+                watch_source_response::Event::ManifestChanged(ManifestChanged {
+                    version: manifest.version().to_string(),
+                    added: diff.added().iter().map(ItemRef::from).collect(),
+                    removed: diff.removed().iter().map(ItemRef::from).collect(),
+                    changed: diff
+                        .changed()
+                        .iter()
+                        .map(|(_, item)| ItemRef::from(item))
+                        .collect(),
+                })
+            }
+            SourceChange::DeliveryMaterialized { name, path, item } => {
+                watch_source_response::Event::DeliveryMaterialized(DeliveryMaterialized {
+                    name: name.clone(),
+                    path: path.display().to_string(),
+                    item: item.clone(),
+                })
+            }
+            SourceChange::DeliveryRevoked { name, path } => {
+                watch_source_response::Event::DeliveryRevoked(DeliveryRevoked {
+                    name: name.clone(),
+                    path: path.display().to_string(),
+                })
+            }
+        };
+        WatchSourceResponse {
+            id: value.source(),
+            manifest_id: manifest.id().to_string(),
+            name: manifest.name().to_string(),
+            labels: manifest.labels().to_vec(),
+            time: Some(value.time().into()),
+            event: Some(event),
+        }
+    }
+}
@@ -0,0 +1,121 @@
+use prost_types::FieldMask;
+use toml::{Value, map::Map};
+
+use super::proto::{
+    FilterPropertyMap, FilterScalarString, FilterVectorString,
+    filter_property_map::{Entry, entry::ValueType},
+};
+
+impl FilterScalarString {
+    pub fn matches(&self, value: &str) -> bool {
+        (self.include.is_empty() || self.include.iter().any(|include| include == value))
+            && !self.exclude.iter().any(|exclude| exclude == value)
+    }
+}
+impl FilterVectorString {
+    pub fn matches(&self, values: &[String]) -> bool {
+        let included = if self.include_exact {
+            self.include.len() == values.len()
+                && self.include.iter().all(|include| values.contains(include))
+        } else {
+            self.include.iter().all(|include| values.contains(include))
+        };
+        included && !self.exclude.iter().any(|exclude| values.contains(exclude))
+    }
+}
+impl FilterPropertyMap {
+    pub fn matches(&self, properties: &Map<String, Value>) -> bool {
+        self.entries.iter().all(|entry| entry.matches(properties))
+    }
+}
+impl Entry {
+    pub fn matches(&self, properties: &Map<String, Value>) -> bool {
+        let typed = |value: &Value| {
+            let ty = ValueType::from(value) as i32;
+            (self.include_type.is_empty() || self.include_type.contains(&ty))
+                && !self.exclude_type.contains(&ty)
+        };
+        let included = if self.include.is_empty() {
+            properties.values().all(typed)
+        } else {
+            self.include
+                .iter()
+                .all(|key| properties.get(key).is_some_and(typed))
+        };
+        included && !self.exclude.iter().any(|key| properties.contains_key(key))
+    }
+}
+impl From<&Value> for ValueType {
+    fn from(value: &Value) -> Self {
+        match value {
+            Value::String(_) | Value::Datetime(_) => ValueType::String,
+            Value::Integer(_) => ValueType::Integer,
+            Value::Float(_) => ValueType::Float,
+            Value::Boolean(_) => ValueType::Boolean,
+            Value::Array(_) => ValueType::Array,
+            Value::Table(_) => ValueType::Table,
+        }
+    }
+}
+/// Field mask over a [`super::proto::Manifest`]; an empty mask selects every field but those
+/// in [`Self::EXPLICIT`], which are only selected when named.
+#[derive(Debug, Clone, Default)]
+pub struct ManifestMask(Vec<String>);
+impl ManifestMask {
+    pub const EXPLICIT: &'static [&'static str] = &["items.value"];
+    pub fn contains(&self, path: &str) -> bool {
+        if Self::EXPLICIT.contains(&path) {
+            return self.0.iter().any(|mask| mask == path);
+        }
+        self.0.is_empty()
+            || self.0.iter().any(|mask| {
+                path == mask
+                    || path
+                        .strip_prefix(mask.as_str())
+                        .is_some_and(|rest| rest.starts_with('.'))
+            })
+    }
+    pub fn select<T: Default>(&self, path: &str, value: impl FnOnce() -> T) -> T {
+        if self.contains(path) {
+            value()
+        } else {
+            T::default()
+        }
+    }
+    pub fn touches(&self, path: &str) -> bool {
+        self.contains(path)
+            || self.0.iter().any(|mask| {
+                mask.strip_prefix(path)
+                    .is_some_and(|rest| rest.starts_with('.'))
+            })
+    }
+}
+impl From<Option<FieldMask>> for ManifestMask {
+    fn from(value: Option<FieldMask>) -> Self {
+        Self(value.map(|mask| mask.paths).unwrap_or_default())
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mask(paths: &[&str]) -> ManifestMask {
+        ManifestMask::from(Some(FieldMask {
+            paths: paths.iter().map(|path| path.to_string()).collect(),
+        }))
+    }
+    #[test]
+    fn empty_mask_leaves_out_values() {
+        let mask = mask(&[]);
+        assert!(mask.contains("items.name"));
+        assert!(mask.touches("items"));
+        assert!(!mask.contains("items.value"));
+    }
+    #[test]
+    fn values_are_selected_only_by_name() {
+        assert!(!mask(&["items"]).contains("items.value"));
+        assert!(mask(&["items.value"]).contains("items.value"));
+        assert!(mask(&["items.value"]).touches("items"));
+        assert!(!mask(&["items.value"]).contains("items.name"));
+    }
+}
@@ -0,0 +1,89 @@
+use std::{pin::Pin, result::Result, sync::Arc};
+
+use bytes::Bytes;
+use tokio_stream::Stream;
+use tonic::{Request, Response, Status};
+use zeroize::Zeroizing;
+
+use crate::{Error, model::HairpinDaemon, policy::Caller};
+
+pub use super::proto::{
+    GetItemRequest, GetItemResponse, ItemValue, ReadItemRequest, ReadItemResponse,
+    hairpin_item_service_server::*, read_item_request,
+};
+
+#[derive(Debug, Clone)]
+pub struct Service(Arc<HairpinDaemon>);
+impl Service {
+    /// Largest chunk of an item value sent in one `read` response. Values are checked whole before
+    /// the first chunk goes out, chunks only keep messages small and share the value's buffer,
+    /// which is zeroed once the last of them is sent.
+    pub const CHUNK_SIZE: usize = 64 * 1024;
+    pub fn new(daemon: Arc<HairpinDaemon>) -> Self {
+        Self(daemon)
+    }
+}
+#[tonic::async_trait]
+impl HairpinItemService for Service {
+    type readStream = Pin<Box<dyn Stream<Item = Result<ReadItemResponse, Status>> + Send>>;
+    async fn get(
+        &self,
+        request: Request<GetItemRequest>,
+    ) -> Result<Response<GetItemResponse>, Status> {
+        Ok(Response::new(Self::get(&self, request).await?))
+    }
+    async fn read(
+        &self,
+        request: Request<ReadItemRequest>,
+    ) -> Result<Response<Self::readStream>, Status> {
+        let value = Bytes::from_owner(Self::read(&self, request).await?);
+        let chunks = (0..value.len())
+            .step_by(Self::CHUNK_SIZE)
+            .map(move |start| ReadItemResponse {
+                chunk: value.slice(start..value.len().min(start + Self::CHUNK_SIZE)),
+            });
+        Ok(Response::new(Box::pin(tokio_stream::iter(chunks.map(Ok)))))
+    }
+}
+impl Service {
+    async fn get(&self, request: Request<GetItemRequest>) -> Result<GetItemResponse, Error> {
+        let caller = Caller::of(&request).await;
+        let request = request.into_inner();
+        let items = self
+            .0
+            .get_items(&caller, request.source, &request.items, &request.labels)
+            .await?;
+        Ok(GetItemResponse {
+            items: items
+                .into_iter()
+                .map(|(item, value)| ItemValue {
+                    id: item.id().to_string(),
+                    name: item.name().to_string(),
+                    value,
+                })
+                .collect(),
+        })
+    }
+    async fn read(&self, request: Request<ReadItemRequest>) -> Result<Zeroizing<Vec<u8>>, Error> {
+        let caller = Caller::of(&request).await;
+        let request = request.into_inner();
+        let value = match request
+            .item
+            .ok_or(Error::InvalidRequest("an item id or name is required"))?
+        {
+            read_item_request::Item::Id(id) => {
+                self.0
+                    .get_item(&caller, request.source_id, &id, |item| item.id() == id)
+                    .await
+            }
+            read_item_request::Item::Name(name) => {
+                self.0
+                    .get_item(&caller, request.source_id, &name, |item| {
+                        item.name() == name
+                    })
+                    .await
+            }
+        }?;
+        Ok(Zeroizing::new(value))
+    }
+}
@@ -1,3 +1,7 @@
+mod convert;
+pub mod filter;
+pub mod item;
+pub mod policy;
 mod service;
 pub mod source;
 pub use service::*;
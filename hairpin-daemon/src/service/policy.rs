@@ -0,0 +1,85 @@
+use std::{result::Result, str::FromStr, sync::Arc};
+
+use tonic::{Request, Response, Status};
+
+use crate::{
+    Error,
+    model::HairpinDaemon,
+    policy::{Action, Caller},
+};
+
+pub use super::proto::{
+    ExplainRequest, ExplainResponse, Subject, hairpin_policy_service_server::*,
+};
+
+#[derive(Debug, Clone)]
+pub struct Service(Arc<HairpinDaemon>);
+impl Service {
+    pub fn new(daemon: Arc<HairpinDaemon>) -> Self {
+        Self(daemon)
+    }
+}
+#[tonic::async_trait]
+impl HairpinPolicyService for Service {
+    async fn explain(
+        &self,
+        request: Request<ExplainRequest>,
+    ) -> Result<Response<ExplainResponse>, Status> {
+        Ok(Response::new(Self::explain(&self, request).await?))
+    }
+}
+impl Service {
+    /// Evaluates the policy without acting. Callers need the explain action on the source to ask
+    /// for another subject, and to ask for themselves either that or the list action.
+    async fn explain(&self, request: Request<ExplainRequest>) -> Result<ExplainResponse, Error> {
+        let caller = Caller::of(&request).await;
+        let request = request.into_inner();
+        let action = Action::from_str(&request.action)?;
+        let manifests = self.0.manifests().read().await;
+        let source = manifests
+            .get(&request.source_id)
+            .ok_or(Error::UnknownSource(request.source_id))?
+            .read()
+            .await;
+        let manifest = source.manifest();
+        let policy = self.0.policy();
+        let required = match &request.subject {
+            Some(_) => Action::Explain,
+            None if policy.allows(&caller, Action::Explain, manifest, None) => Action::Explain,
+            None => Action::List,
+        };
+        self.0.authorize(&caller, required, manifest, None).await?;
+        let subject = match request.subject {
+            Some(subject) => Caller::subject(
+                subject.uid,
+                subject.gid,
+                subject.unit,
+                subject.exe.map(Into::into),
+            ),
+            None => caller,
+        };
+        let item = match &request.item {
+            Some(key) => Some(
+                manifest
+                    .items()
+                    .iter()
+                    .find(|item| item.id() == key)
+                    .or_else(|| manifest.items().iter().find(|item| item.name() == key))
+                    .ok_or_else(|| Error::UnknownItem(request.source_id, key.clone()))?,
+            ),
+            None => None,
+        };
+        let decision = policy.evaluate(&subject, action, manifest, item);
+        let rule = decision.rule().map(|index| (index, &policy.rules()[index]));
+        Ok(ExplainResponse {
+            allowed: decision.is_allowed(),
+            rule: rule.map(|(index, _)| index as u32),
+            rule_name: rule
+                .and_then(|(_, rule)| rule.name())
+                .unwrap_or_default()
+                .to_string(),
+            effect: decision.effect().to_string(),
+            caller: subject.to_string(),
+        })
+    }
+}
@@ -1,3 +1,4 @@
 pub mod proto {
+    #![allow(non_camel_case_types)]
     tonic::include_proto!("hairpin");
 }
@@ -1,24 +1,38 @@
-use std::{borrow::Cow, result::Result, str::FromStr, sync::Arc};
+use std::{pin::Pin, result::Result, str::FromStr, sync::Arc};
 
 use http::Uri;
-use manifest::ManifestResolver;
-use tokio::sync::RwLock;
+use tokio::sync::broadcast::error::RecvError;
+use tokio_stream::{Stream, StreamExt, wrappers::ReceiverStream};
 use tonic::{Request, Response, Status, service::Interceptor};
+use zeroize::{Zeroize, Zeroizing};
 
 use crate::{
     Error,
+    audit::AuditEvent,
     model::{HairpinDaemon, HairpinSource, HairpinSourceLocation},
+    policy::{Action, Caller},
 };
 
-use super::proto::CreateSourceResponse;
 pub use super::proto::{
-    CreateSourceRequest, DeleteSourceRequest, hairpin_source_service_server::*,
+    CreateSourceRequest, DeleteSourceRequest, ListSourceRequest, ListSourceResponse,
+    UnlockSourceRequest, WatchSourceRequest, WatchSourceResponse, hairpin_source_service_server::*,
+};
+use super::{
+    filter::ManifestMask,
+    proto::{CreateSourceResponse, Manifest, ManifestItem, ManifestSource},
 };
 
 #[derive(Debug, Clone)]
 pub struct Service(Arc<HairpinDaemon>);
+impl Service {
+    pub fn new(daemon: Arc<HairpinDaemon>) -> Self {
+        Self(daemon)
+    }
+}
 #[tonic::async_trait]
 impl HairpinSourceService for Service {
+    type listStream = Pin<Box<dyn Stream<Item = Result<ListSourceResponse, Status>> + Send>>;
+    type watchStream = ReceiverStream<Result<WatchSourceResponse, Status>>;
     async fn delete(&self, request: Request<DeleteSourceRequest>) -> Result<Response<()>, Status> {
         Ok(Response::new(Self::delete(&self, request).await?))
     }
@@ -28,15 +42,103 @@ impl HairpinSourceService for Service {
     ) -> Result<Response<CreateSourceResponse>, Status> {
         Ok(Response::new(Self::create(&self, request).await?))
     }
+    async fn list(
+        &self,
+        request: Request<ListSourceRequest>,
+    ) -> Result<Response<Self::listStream>, Status> {
+        Ok(Response::new(Box::pin(
+            Self::list(&self, request).await.map(Ok),
+        )))
+    }
+    async fn unlock(&self, request: Request<UnlockSourceRequest>) -> Result<Response<()>, Status> {
+        Ok(Response::new(Self::unlock(&self, request).await?))
+    }
+    async fn watch(
+        &self,
+        request: Request<WatchSourceRequest>,
+    ) -> Result<Response<Self::watchStream>, Status> {
+        Ok(Response::new(Self::watch(&self, request).await))
+    }
 }
 impl Service {
     async fn delete(&self, request: Request<DeleteSourceRequest>) -> Result<(), Error> {
-        let mut sources = self.0.manifests().write().await;
-        for id in request.into_inner().ids {
-            sources.remove(&id);
+        let caller = Caller::of(&request).await;
+        let ids = request.into_inner().ids;
+        for id in &ids {
+            self.0
+                .authorize_source(&caller, Action::Delete, *id)
+                .await?;
+        }
+        for (id, source) in self.0.unregister(&ids).await? {
+            self.0
+                .record(AuditEvent::SourceDeleted {
+                    caller: caller.clone(),
+                    source: id,
+                    location: source.location().to_string(),
+                })
+                .await;
         }
         Ok(())
     }
+    async fn unlock(&self, request: Request<UnlockSourceRequest>) -> Result<(), Error> {
+        let caller = Caller::of(&request).await;
+        let mut request = request.into_inner();
+        self.0
+            .authorize_source(&caller, Action::Unlock, request.id)
+            .await?;
+        let passphrase = Zeroizing::new(std::mem::take(&mut request.passphrase));
+        self.0.unlock(request.id, passphrase).await?;
+        self.0
+            .record(AuditEvent::SourceUnlocked {
+                caller,
+                source: request.id,
+            })
+            .await;
+        Ok(())
+    }
+    /// Streams events of the sources the caller may list until the caller hangs up or the daemon
+    /// shuts down. A caller too slow to keep up gets an error once events were dropped.
+    async fn watch(
+        &self,
+        request: Request<WatchSourceRequest>,
+    ) -> ReceiverStream<Result<WatchSourceResponse, Status>> {
+        let caller = Caller::of(&request).await;
+        let labels = request.into_inner().labels;
+        let mut events = self.0.events().subscribe();
+        let (sender, receiver) = tokio::sync::mpsc::channel(16);
+        let daemon = self.0.clone();
+        tokio::spawn(async move {
+            loop {
+                let event = tokio::select! {
+                    event = events.recv() => event,
+                    _ = sender.closed() => break,
+                    _ = daemon.shutdown().cancelled() => break,
+                };
+                let response = match event {
+                    Ok(event) => {
+                        let manifest = event.manifest();
+                        if !daemon
+                            .policy()
+                            .allows(&caller, Action::List, manifest, None)
+                            || !labels
+                                .as_ref()
+                                .is_none_or(|filter| filter.matches(manifest.labels()))
+                        {
+                            continue;
+                        }
+                        Ok(WatchSourceResponse::from(&event))
+                    }
+                    Err(RecvError::Lagged(missed)) => Err(Error::WatchLagged(missed).into()),
+                    Err(RecvError::Closed) => break,
+                };
+                let lagged = response.is_err();
+                if sender.send(response).await.is_err() || lagged {
+                    break;
+                }
+            }
+        });
+        ReceiverStream::new(receiver)
+    }
     async fn create(
         &self,
         request: Request<CreateSourceRequest>,
@@ -46,6 +148,7 @@ impl Service {
             .get::<SourceScheme>()
             .cloned()
             .unwrap_or_default();
+        let caller = Caller::of(&request).await;
         let mut output = Vec::new();
         for source in request
             .into_inner()
@@ -56,36 +159,184 @@ impl Service {
             let source = source?;
             guard.validate(&source)?;
             let location: HairpinSourceLocation = source.try_into()?;
-            let manifest = location.resolve().await?;
+            let manifest = self.0.resolve(&location).await?;
+            self.0
+                .authorize(&caller, Action::Create, &manifest, None)
+                .await?;
             output.push(HairpinSource::new(location, manifest));
         }
-        let mut sources = self.0.manifests().write().await;
-        let mut ids = Vec::new();
-        for source in output {
-            let id = self.0.new_id().await;
-            sources.insert(id, RwLock::new(source));
-            ids.push(id);
+        let locations = output
+            .iter()
+            .map(|source| source.location().to_string())
+            .collect::<Vec<_>>();
+        let ids = self.0.register(output).await?;
+        for (id, location) in ids.iter().zip(locations) {
+            self.0
+                .record(AuditEvent::SourceCreated {
+                    caller: caller.clone(),
+                    source: *id,
+                    location,
+                })
+                .await;
         }
         Ok(CreateSourceResponse { ids })
     }
+    /// Streams the sources the caller may list, each one looked up, filtered and converted only
+    /// once the stream is polled for it.
+    async fn list(
+        &self,
+        request: Request<ListSourceRequest>,
+    ) -> impl Stream<Item = ListSourceResponse> + Send + 'static {
+        let listing = Arc::new(Listing {
+            service: self.clone(),
+            caller: Caller::of(&request).await,
+            mask: ManifestMask::from(request.get_ref().mask.clone()),
+            request: request.into_inner(),
+        });
+        let ids = self
+            .0
+            .manifests()
+            .read()
+            .await
+            .keys()
+            .copied()
+            .collect::<Vec<_>>();
+        tokio_stream::iter(ids)
+            .then(move |id| listing.clone().response(id))
+            .filter_map(|response| response)
+    }
+    /// Reads the raw value of a listed item if the caller may read it.
+    async fn read_listed(
+        &self,
+        caller: &Caller,
+        id: u64,
+        source: &HairpinSource,
+        item: &manifest::Item,
+    ) -> Result<Vec<u8>, Error> {
+        self.0
+            .authorize(caller, Action::Read, source.manifest(), Some(item))
+            .await?;
+        let value = self.0.read_item(source, item).await?;
+        self.0.record_read(caller, id, item).await?;
+        Ok(value)
+    }
+}
+/// A list request, shared by the sources streamed for it.
+struct Listing {
+    service: Service,
+    caller: Caller,
+    request: ListSourceRequest,
+    mask: ManifestMask,
+}
+impl Listing {
+    fn selects(&self, manifest: &manifest::Manifest) -> bool {
+        let request = &self.request;
+        self.service
+            .0
+            .policy()
+            .allows(&self.caller, Action::List, manifest, None)
+            && request
+                .id
+                .as_ref()
+                .is_none_or(|filter| filter.matches(manifest.id()))
+            && request
+                .name
+                .as_ref()
+                .is_none_or(|filter| filter.matches(manifest.name()))
+            && request
+                .version
+                .as_ref()
+                .is_none_or(|filter| filter.matches(manifest.version()))
+            && request
+                .labels
+                .as_ref()
+                .is_none_or(|filter| filter.matches(manifest.labels()))
+            && request
+                .properties
+                .as_ref()
+                .is_none_or(|filter| filter.matches(manifest.properties()))
+    }
+    /// The response for a source, `None` if it is gone or not selected.
+    async fn response(self: Arc<Self>, id: u64) -> Option<ListSourceResponse> {
+        // The source is copied out so values are read without holding the registry lock.
+        let source = {
+            let manifests = self.service.0.manifests().read().await;
+            let source = manifests.get(&id)?.read().await;
+            if !self.selects(source.manifest()) {
+                return None;
+            }
+            source.clone()
+        };
+        let mask = &self.mask;
+        let manifest = source.manifest();
+        let mut items = Vec::new();
+        if mask.touches("items") {
+            for item in manifest.items() {
+                // Denied or unreadable values are reported per item instead of failing the list.
+                let (value, value_error) = match mask.contains("items.value") {
+                    true => match self
+                        .service
+                        .read_listed(&self.caller, id, &source, item)
+                        .await
+                    {
+                        Ok(value) => (value, None),
+                        Err(err) => (Vec::new(), Some(err.to_string())),
+                    },
+                    false => (Vec::new(), None),
+                };
+                items.push(ManifestItem {
+                    id: mask.select("items.id", || item.id().to_string()),
+                    name: mask.select("items.name", || item.name().to_string()),
+                    value,
+                    encryption: mask.select("items.encryption", || item.encryption().to_string()),
+                    properties: mask.select("items.properties", || Some(item.properties().into())),
+                    labels: mask.select("items.labels", || item.labels().to_vec()),
+                    value_error,
+                });
+            }
+        }
+        Some(ListSourceResponse {
+            id,
+            manifest: Some(Manifest {
+                id: mask.select("id", || manifest.id().to_string()),
+                name: mask.select("name", || manifest.name().to_string()),
+                version: mask.select("version", || manifest.version().to_string()),
+                items,
+                properties: mask.select("properties", || Some(manifest.properties().into())),
+                labels: mask.select("labels", || manifest.labels().to_vec()),
+            }),
+            source: ManifestSource::from(source.origin()) as i32,
+        })
+    }
+}
+/// Wipes the passphrase of unlock requests, on the client as on the daemon.
+impl Drop for UnlockSourceRequest {
+    fn drop(&mut self) {
+        self.passphrase.zeroize();
+    }
 }
 #[derive(Debug, Clone, Default)]
-pub struct SourceScheme<'a>(Cow<'a, [&'a str]>);
+pub struct SourceScheme(Arc<[String]>);
 #[derive(Debug, Clone)]
-pub struct SourceSchemeGuard<'a>(SourceScheme<'a>);
+pub struct SourceSchemeGuard(SourceScheme);
+impl SourceSchemeGuard {
+    pub fn new(schemes: impl IntoIterator<Item = String>) -> Self {
+        Self(SourceScheme(schemes.into_iter().collect()))
+    }
+}
 
-impl Interceptor for SourceSchemeGuard<'static> {
+impl Interceptor for SourceSchemeGuard {
     fn call(&mut self, mut request: tonic::Request<()>) -> Result<tonic::Request<()>, Status> {
         request.extensions_mut().insert(self.0.clone());
         Ok(request)
     }
 }
 
-impl<'a> SourceScheme<'a> {
+impl SourceScheme {
     fn validate(&self, value: &Uri) -> Result<(), Error> {
         if value
             .scheme_str()
-            .is_some_and(|scheme| self.0.contains(&scheme))
+            .is_some_and(|scheme| self.0.iter().any(|allowed| allowed == scheme))
         {
             Ok(())
         } else {
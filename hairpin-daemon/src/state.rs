@@ -0,0 +1,92 @@
+use std::{
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use http::Uri;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+use crate::{Error, model::HairpinSourceLocation};
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct State {
+    next_id: u64,
+    #[serde(default, rename = "source")]
+    sources: Vec<StateSource>,
+}
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StateSource {
+    id: u64,
+    location: String,
+}
+impl State {
+    pub fn next_id(&self) -> u64 {
+        self.sources
+            .iter()
+            .map(|source| source.id + 1)
+            .fold(self.next_id, u64::max)
+    }
+    pub fn sources(&self) -> &[StateSource] {
+        &self.sources
+    }
+}
+impl StateSource {
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+    pub fn location(&self) -> Result<HairpinSourceLocation, Error> {
+        Uri::from_str(self.location.as_str())?.try_into()
+    }
+}
+/// Registered sources, persisted as `sources.toml` in the daemon's state directory.
+#[derive(Debug)]
+pub struct StateStore {
+    path: PathBuf,
+    state: Mutex<State>,
+}
+impl StateStore {
+    pub const NAME: &'static str = "sources.toml";
+    pub async fn open(directory: impl AsRef<Path>) -> Result<Self, Error> {
+        let directory = directory.as_ref();
+        tokio::fs::create_dir_all(directory).await?;
+        let path = directory.join(Self::NAME);
+        let state = if tokio::fs::try_exists(&path).await? {
+            toml::from_str(tokio::fs::read_to_string(&path).await?.as_str())?
+        } else {
+            State::default()
+        };
+        Ok(Self {
+            path,
+            state: Mutex::new(state),
+        })
+    }
+    pub async fn state(&self) -> State {
+        self.state.lock().await.clone()
+    }
+    pub async fn insert(
+        &self,
+        sources: impl IntoIterator<Item = (u64, &HairpinSourceLocation)>,
+    ) -> Result<(), Error> {
+        let mut state = self.state.lock().await;
+        for (id, location) in sources {
+            state.sources.push(StateSource {
+                id,
+                location: location.to_string(),
+            });
+            state.next_id = state.next_id.max(id + 1);
+        }
+        self.flush(&state).await
+    }
+    pub async fn remove(&self, ids: &[u64]) -> Result<(), Error> {
+        let mut state = self.state.lock().await;
+        state.sources.retain(|source| !ids.contains(&source.id));
+        self.flush(&state).await
+    }
+    async fn flush(&self, state: &State) -> Result<(), Error> {
+        let temporary = self.path.with_extension("toml.tmp");
+        tokio::fs::write(&temporary, toml::to_string_pretty(state)?).await?;
+        tokio::fs::rename(&temporary, &self.path).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,23 @@
+use clap::Subcommand;
+
+use crate::Resolver;
+
+use super::verify::VerifyAuditArgs;
+
+#[derive(Debug, Subcommand)]
+pub enum AuditCommands {
+    /// Checks the hash chain of an audit log and its rotated files
+    #[command(arg_required_else_help = true)]
+    Verify(VerifyAuditArgs),
+}
+impl Resolver for AuditCommands {
+    type Context = ();
+
+    type Error = crate::Error;
+
+    fn resolve(self, context: Self::Context) -> Result<(), Self::Error> {
+        match self {
+            AuditCommands::Verify(value) => value.resolve(context),
+        }
+    }
+}
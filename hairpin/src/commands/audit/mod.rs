@@ -0,0 +1,3 @@
+mod audit;
+pub mod verify;
+pub use audit::*;
@@ -0,0 +1,27 @@
+use std::path::PathBuf;
+
+use clap::Args;
+use hairpin_daemon::audit::{self, AuditKey};
+
+use crate::Resolver;
+
+#[derive(Debug, Args)]
+pub struct VerifyAuditArgs {
+    /// Audit log written by a daemon started with `--audit-chain`
+    log: PathBuf,
+    /// Key the log was chained with, the daemon's `--audit-key`
+    #[arg(long)]
+    key: PathBuf,
+}
+impl Resolver for VerifyAuditArgs {
+    type Context = ();
+
+    type Error = crate::Error;
+
+    fn resolve(self, _: Self::Context) -> Result<(), Self::Error> {
+        let key = AuditKey::read(&self.key)?;
+        let count = audit::verify(&self.log, &key)?;
+        println!("{count} entries of {} verified", self.log.display());
+        Ok(())
+    }
+}
@@ -1,6 +1,7 @@
 use std::error::Error;
 
 use clap::Subcommand;
+use toml::{Value, map::Map};
 
 use crate::Resolver;
 
@@ -8,7 +9,21 @@ use crate::Resolver;
 pub enum Commands {
     #[command(subcommand)]
     Create(super::create::CreateCommands),
-    Start(hairpin_daemon::model::HairpinDaemonOptions),
+    #[command(subcommand)]
+    Source(super::source::SourceCommands),
+    #[command(subcommand)]
+    Item(super::item::ItemCommands),
+    #[command(subcommand)]
+    Policy(super::policy::PolicyCommands),
+    #[command(subcommand)]
+    Audit(super::audit::AuditCommands),
+    #[command(subcommand)]
+    Config(super::config::ConfigCommands),
+    Start(Box<hairpin_daemon::model::HairpinDaemonOptions>),
+    /// Runs a command with decrypted item values in its environment
+    #[command(arg_required_else_help = true)]
+    Exec(super::exec::ExecArgs),
+    Watch(super::watch::WatchArgs),
 }
 impl Resolver for Commands {
     type Context = ();
@@ -18,20 +33,31 @@ impl Resolver for Commands {
     fn resolve(self, context: Self::Context) -> Result<(), Self::Error> {
         match self {
             Commands::Create(value) => Ok(value.resolve(context)?),
+            Commands::Source(value) => Ok(value.resolve(context)?),
+            Commands::Item(value) => Ok(value.resolve(context)?),
+            Commands::Policy(value) => value.resolve(context),
+            Commands::Audit(value) => value.resolve(context),
+            Commands::Config(value) => value.resolve(context),
             Commands::Start(value) => Ok(value.resolve(context)?),
+            Commands::Exec(value) => value.resolve(context),
+            Commands::Watch(value) => value.resolve(context),
         }
     }
 }
 
-pub fn parse_property<T, U>(s: &str) -> Result<(T, U), Box<dyn Error + Send + Sync + 'static>>
-where
-    T: std::str::FromStr,
-    T::Err: Error + Send + Sync + 'static,
-    U: std::str::FromStr,
-    U::Err: Error + Send + Sync + 'static,
-{
-    let pos = s
-        .find('=')
+/// Parses `KEY=value`, reading the value as a TOML value and falling back to a plain string.
+pub fn parse_property(s: &str) -> Result<(String, Value), Box<dyn Error + Send + Sync + 'static>> {
+    let (key, value) = s
+        .split_once('=')
         .ok_or_else(|| format!("invalid KEY=value: no `=` found in `{s}`"))?;
-    Ok((s[..pos].parse()?, s[pos + 1..].parse()?))
+    let value = match toml::from_str::<Map<String, Value>>(format!("value = {value}").as_str()) {
+        Ok(table) if table.len() > 1 => {
+            return Err(format!("invalid KEY=value: `{s}` sets more than one key").into());
+        }
+        Ok(mut table) => table
+            .remove("value")
+            .unwrap_or_else(|| Value::String(value.to_string())),
+        Err(_) => Value::String(value.to_string()),
+    };
+    Ok((key.to_string(), value))
 }
@@ -0,0 +1,39 @@
+use std::path::PathBuf;
+
+use clap::Args;
+use hairpin_daemon::model::HairpinDaemonOptions;
+
+use crate::{Resolver, commands::connect::runtime};
+
+#[derive(Debug, Args)]
+pub struct CheckConfigArgs {
+    /// Config file, the daemon's default one when omitted
+    config: Option<PathBuf>,
+}
+impl Resolver for CheckConfigArgs {
+    type Context = ();
+
+    type Error = crate::Error;
+
+    fn resolve(self, _: Self::Context) -> Result<(), Self::Error> {
+        let config = self
+            .config
+            .unwrap_or_else(|| PathBuf::from(HairpinDaemonOptions::DEFAULT_CONFIG));
+        let (warnings, problems) = runtime()?.block_on(async {
+            let options = HairpinDaemonOptions::load(&config).await?;
+            Ok::<_, crate::Error>((options.warnings(), options.check().await))
+        })?;
+        for (setting, warning) in &warnings {
+            println!("{}: {setting}: warning: {warning}", config.display());
+        }
+        for (setting, problem) in &problems {
+            println!("{}: {setting}: {problem}", config.display());
+        }
+        println!("{}: {} error(s)", config.display(), problems.len());
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(crate::Error::CheckFailed(problems.len()))
+        }
+    }
+}
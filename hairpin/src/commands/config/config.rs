@@ -0,0 +1,22 @@
+use clap::Subcommand;
+
+use crate::Resolver;
+
+use super::check::CheckConfigArgs;
+
+#[derive(Debug, Subcommand)]
+pub enum ConfigCommands {
+    /// Validates a daemon config file and the files it refers to
+    Check(CheckConfigArgs),
+}
+impl Resolver for ConfigCommands {
+    type Context = ();
+
+    type Error = crate::Error;
+
+    fn resolve(self, context: Self::Context) -> Result<(), Self::Error> {
+        match self {
+            ConfigCommands::Check(value) => value.resolve(context),
+        }
+    }
+}
@@ -0,0 +1,3 @@
+pub mod check;
+mod config;
+pub use config::*;
@@ -0,0 +1,42 @@
+use std::path::PathBuf;
+
+use clap::Args;
+use hairpin_daemon::model::HairpinDaemonOptions;
+use hyper_util::rt::TokioIo;
+use tokio::net::UnixStream;
+use tonic::transport::{Channel, Endpoint, Uri};
+use tower::service_fn;
+
+#[derive(Debug, Clone, Args)]
+pub struct ConnectArgs {
+    #[arg(long = "socket")]
+    socket: Option<PathBuf>,
+    #[arg(long = "address", conflicts_with = "socket")]
+    address: Option<String>,
+}
+impl ConnectArgs {
+    pub async fn connect(&self) -> Result<Channel, crate::Error> {
+        if let Some(address) = &self.address {
+            return Ok(Endpoint::from_shared(address.clone())?.connect().await?);
+        }
+        let socket = self
+            .socket
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(HairpinDaemonOptions::DEFAULT_SOCKET));
+        Ok(
+            Endpoint::from_static("http://[::]:50051")
+                .connect_with_connector(service_fn(move |_: Uri| {
+                    let socket = socket.clone();
+                    async move {
+                        Ok::<_, std::io::Error>(TokioIo::new(UnixStream::connect(socket).await?))
+                    }
+                }))
+                .await?,
+        )
+    }
+}
+pub fn runtime() -> std::io::Result<tokio::runtime::Runtime> {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+}
@@ -15,7 +15,7 @@ pub struct CreateSourceArgs {
     name: Option<String>,
     #[arg(short = 'l', long = "label")]
     labels: Vec<String>,
-    #[arg(short = 'p', long = "property",value_parser = parse_property::<String,toml::Value>)]
+    #[arg(short = 'p', long = "property",value_parser = parse_property)]
     properties: Vec<(String, Value)>,
 }
 impl Resolver for CreateSourceArgs {
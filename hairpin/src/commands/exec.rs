@@ -0,0 +1,110 @@
+use std::{
+    collections::HashMap,
+    ffi::OsString,
+    os::unix::{ffi::OsStringExt, process::CommandExt},
+    process::Command,
+};
+
+use clap::Args;
+use hairpin_daemon::service::proto::{
+    GetItemRequest, hairpin_item_service_client::HairpinItemServiceClient,
+};
+
+use crate::{
+    Resolver,
+    commands::connect::{ConnectArgs, runtime},
+};
+
+#[derive(Debug, Args)]
+pub struct ExecArgs {
+    #[command(flatten)]
+    connect: ConnectArgs,
+    #[arg(short = 's', long = "source")]
+    source: u64,
+    /// Item id or name to inject, as ITEM=ENV_VAR or ITEM to use the item name
+    #[arg(short = 'i', long = "item", value_parser = parse_mapping)]
+    items: Vec<(String, Option<String>)>,
+    /// Injects every item with this label under its item name
+    #[arg(short = 'l', long = "label")]
+    labels: Vec<String>,
+    #[arg(required = true, last = true)]
+    command: Vec<OsString>,
+}
+impl Resolver for ExecArgs {
+    type Context = ();
+
+    type Error = crate::Error;
+
+    fn resolve(self, _: Self::Context) -> Result<(), Self::Error> {
+        let request = GetItemRequest {
+            source: self.source,
+            items: self.items.iter().map(|(item, _)| item.clone()).collect(),
+            labels: self.labels,
+        };
+        let response = runtime()?.block_on(async {
+            let mut client = HairpinItemServiceClient::new(self.connect.connect().await?);
+            Ok::<_, crate::Error>(client.get(request).await?.into_inner())
+        })?;
+        let mut mapped = HashMap::<&str, Vec<Option<&str>>>::new();
+        for (item, variable) in &self.items {
+            mapped
+                .entry(item.as_str())
+                .or_default()
+                .push(variable.as_deref());
+        }
+        let mut environment = Vec::new();
+        for item in response.items {
+            // Mappings by id and by name both apply, items only selected by label use their name.
+            let mut variables = [item.id.as_str(), item.name.as_str()]
+                .iter()
+                .filter_map(|key| mapped.get(key))
+                .flatten()
+                .copied()
+                .collect::<Vec<_>>();
+            if variables.is_empty() {
+                variables.push(None);
+            }
+            let mut variables = variables
+                .into_iter()
+                .map(|variable| variable.unwrap_or(&item.name))
+                .collect::<Vec<_>>();
+            variables.sort_unstable();
+            variables.dedup();
+            for variable in variables {
+                if !is_variable(variable) {
+                    return Err(crate::Error::InvalidVariableName(variable.to_string()));
+                }
+                if item.value.contains(&0) {
+                    return Err(crate::Error::InvalidVariable(variable.to_string()));
+                }
+                environment.push((variable.to_string(), item.value.clone()));
+            }
+        }
+        let (program, args) = self.command.split_first().ok_or(crate::Error::Undefined)?;
+        let mut command = Command::new(program);
+        command.args(args);
+        for (variable, value) in environment {
+            command.env(variable, OsString::from_vec(value));
+        }
+        Err(command.exec().into())
+    }
+}
+fn parse_mapping(s: &str) -> Result<(String, Option<String>), String> {
+    let (item, variable) = match s.split_once('=') {
+        Some((item, variable)) => (item, Some(variable)),
+        None => (s, None),
+    };
+    if item.is_empty() || variable.is_some_and(|variable| !is_variable(variable)) {
+        return Err(format!("invalid ITEM=ENV_VAR mapping `{s}`"));
+    }
+    Ok((item.to_string(), variable.map(str::to_string)))
+}
+/// Portable variable names, letters, digits and underscores not starting with a digit.
+fn is_variable(name: &str) -> bool {
+    name.chars()
+        .next()
+        .is_some_and(|first| !first.is_ascii_digit())
+        && name
+            .chars()
+            .all(|char| char.is_ascii_alphanumeric() || char == '_')
+}
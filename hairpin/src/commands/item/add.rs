@@ -0,0 +1,162 @@
+use std::{io::Write, os::unix::fs::OpenOptionsExt, path::PathBuf, str::FromStr};
+
+use clap::{Args, ValueEnum};
+use manifest::{
+    Item, ItemEncryption, KdfParameters, Manifest, ValueAccessor,
+    passphrase::{self, Key},
+    value::sha256,
+};
+use uuid::Uuid;
+
+use toml::{Value, map::Map};
+
+use crate::{
+    Resolver,
+    commands::{parse_property, passphrase::read_passphrase},
+};
+
+use super::{Error, read_manifest, write_manifest};
+
+#[derive(Debug, Args)]
+pub struct AddItemArgs {
+    source: PathBuf,
+    #[arg(short = 'n', long = "name")]
+    name: String,
+    #[arg(short = 'f', long = "file")]
+    file: PathBuf,
+    #[arg(short = 'l', long = "label")]
+    labels: Vec<String>,
+    #[arg(short = 'p', long = "property", value_parser = parse_property)]
+    properties: Vec<(String, Value)>,
+    #[arg(short = 'e', long = "encrypt", value_enum)]
+    encrypt: Option<Encrypt>,
+    #[arg(short = 'r', long = "recipient")]
+    recipients: Vec<String>,
+    #[arg(long = "passphrase-file")]
+    passphrase_file: Option<PathBuf>,
+}
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Encrypt {
+    None,
+    Age,
+    Passphrase,
+}
+impl Resolver for AddItemArgs {
+    type Context = ();
+
+    type Error = Error;
+
+    fn resolve(self, _: Self::Context) -> Result<(), Self::Error> {
+        let mut manifest = read_manifest(&self.source)?;
+        if manifest.items().iter().any(|item| item.name() == self.name) {
+            return Err(Error::DuplicateItem(self.name));
+        }
+        let value = std::fs::read(&self.file)?;
+        let id = Uuid::new_v4().to_string();
+        let (value, encryption, path) = match self.encryption()? {
+            Encrypt::None => (
+                value,
+                ItemEncryption::None,
+                PathBuf::from("items").join(&id),
+            ),
+            Encrypt::Age => (
+                encrypt_age(&self.recipients, &value)?,
+                ItemEncryption::Age,
+                PathBuf::from("items").join(format!("{id}.age")),
+            ),
+            Encrypt::Passphrase => {
+                let (parameters, key) = self.passphrase_key(&manifest)?;
+                (
+                    passphrase::seal(&key, &value)?,
+                    ItemEncryption::Passphrase(parameters),
+                    PathBuf::from("items").join(format!("{id}.enc")),
+                )
+            }
+        };
+        std::fs::create_dir_all(self.source.join("items"))?;
+        let file = self.source.join(&path);
+        std::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .mode(0o600)
+            .open(&file)?
+            .write_all(&value)
+            .inspect_err(|_| {
+                let _ = std::fs::remove_file(&file);
+            })?;
+        let mut item = Item::builder();
+        item.set_id(id);
+        item.set_name(self.name);
+        item.set_value(ValueAccessor::Path(path));
+        item.set_sha256(Some(sha256(&value)));
+        item.set_encryption(encryption);
+        item.set_properties(self.properties.into_iter().collect::<Map<_, _>>());
+        for label in self.labels {
+            item.with_label(label);
+        }
+        manifest.add_item(item.build());
+        // Without the manifest nothing refers to the value, so don't leave it behind.
+        write_manifest(&self.source, &manifest).inspect_err(|_| {
+            let _ = std::fs::remove_file(&file);
+        })
+    }
+}
+impl AddItemArgs {
+    /// Recipients imply age encryption when no mode is given.
+    fn encryption(&self) -> Result<Encrypt, Error> {
+        match (self.encrypt, self.recipients.is_empty()) {
+            (None, true) => Ok(Encrypt::None),
+            (None, false) => Ok(Encrypt::Age),
+            (Some(Encrypt::Age), true) => Err(Error::MissingRecipients),
+            (Some(Encrypt::Age), false) => Ok(Encrypt::Age),
+            (Some(_), false) => Err(Error::UnexpectedRecipients),
+            (Some(encrypt), true) => Ok(encrypt),
+        }
+    }
+    /// Derives the source's passphrase key, reusing and checking against existing passphrase items.
+    fn passphrase_key(&self, manifest: &Manifest) -> Result<(KdfParameters, Key), Error> {
+        let existing =
+            manifest
+                .items()
+                .iter()
+                .find_map(|item| match (item.encryption(), item.value()) {
+                    (ItemEncryption::Passphrase(parameters), ValueAccessor::Path(path)) => {
+                        Some((parameters, path))
+                    }
+                    _ => None,
+                });
+        let passphrase = read_passphrase(self.passphrase_file.as_deref(), existing.is_none())?;
+        match existing {
+            Some((parameters, path)) => {
+                let key = parameters.derive(passphrase.as_bytes())?;
+                passphrase::open(&key, &std::fs::read(self.source.join(path))?)
+                    .map_err(|_| Error::IncorrectPassphrase)?;
+                Ok((parameters.clone(), key))
+            }
+            None => {
+                let parameters = KdfParameters::generate();
+                let key = parameters.derive(passphrase.as_bytes())?;
+                Ok((parameters, key))
+            }
+        }
+    }
+}
+pub fn encrypt_age(recipients: &[String], value: &[u8]) -> Result<Vec<u8>, Error> {
+    let recipients = recipients
+        .iter()
+        .map(|recipient| {
+            age::x25519::Recipient::from_str(recipient)
+                .map_err(|err| Error::InvalidRecipient(recipient.clone(), err))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let encryptor = age::Encryptor::with_recipients(
+        recipients
+            .iter()
+            .map(|recipient| recipient as &dyn age::Recipient),
+    )?;
+    let mut output = Vec::new();
+    let mut writer = encryptor.wrap_output(&mut output)?;
+    writer.write_all(value)?;
+    writer.finish()?;
+    Ok(output)
+}
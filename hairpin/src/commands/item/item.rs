@@ -0,0 +1,74 @@
+use std::path::Path;
+
+use clap::Subcommand;
+use manifest::Manifest;
+
+use crate::Resolver;
+
+use super::{add::AddItemArgs, ls::ListItemArgs, rm::RemoveItemArgs};
+
+#[derive(Debug, Subcommand)]
+pub enum ItemCommands {
+    #[command(arg_required_else_help = true)]
+    Add(AddItemArgs),
+    #[command(arg_required_else_help = true)]
+    Rm(RemoveItemArgs),
+    #[command(arg_required_else_help = true)]
+    Ls(ListItemArgs),
+}
+impl Resolver for ItemCommands {
+    type Context = ();
+
+    type Error = crate::Error;
+
+    fn resolve(self, context: Self::Context) -> Result<(), Self::Error> {
+        match self {
+            ItemCommands::Add(value) => Ok(value.resolve(context)?),
+            ItemCommands::Rm(value) => Ok(value.resolve(context)?),
+            ItemCommands::Ls(value) => value.resolve(context),
+        }
+    }
+}
+pub fn read_manifest(source: &Path) -> Result<Manifest, Error> {
+    Ok(toml::from_str(
+        std::fs::read_to_string(source.join(Manifest::NAME))?.as_str(),
+    )?)
+}
+pub fn write_manifest(source: &Path, manifest: &Manifest) -> Result<(), Error> {
+    let path = source.join(Manifest::NAME);
+    let temporary = path.with_extension("toml.tmp");
+    std::fs::write(&temporary, toml::to_string_pretty(manifest)?)
+        .and_then(|_| std::fs::rename(&temporary, &path))
+        .inspect_err(|_| {
+            let _ = std::fs::remove_file(&temporary);
+        })?;
+    if source.join(Manifest::SIGNATURE_NAME).exists() {
+        eprintln!("The manifest signature is now stale, sign the source again");
+    }
+    Ok(())
+}
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("invalid recipient {0}: {1}")]
+    InvalidRecipient(String, &'static str),
+    #[error(transparent)]
+    Encryption(#[from] age::EncryptError),
+    #[error(transparent)]
+    Passphrase(#[from] manifest::passphrase::Error),
+    #[error("Passphrase does not match the existing passphrase items")]
+    IncorrectPassphrase,
+    #[error("age encryption requires at least one recipient")]
+    MissingRecipients,
+    #[error("recipients are only used with age encryption")]
+    UnexpectedRecipients,
+    #[error("no item with id or name {0}")]
+    ItemNotFound(String),
+    #[error("an item named {0} already exists")]
+    DuplicateItem(String),
+    #[error(transparent)]
+    IO(#[from] std::io::Error),
+    #[error(transparent)]
+    InvalidManifest(#[from] toml::de::Error),
+    #[error(transparent)]
+    Serialization(#[from] toml::ser::Error),
+}
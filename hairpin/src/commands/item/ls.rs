@@ -0,0 +1,59 @@
+use std::path::PathBuf;
+
+use clap::Args;
+use manifest::ValueAccessor;
+
+use crate::{
+    Resolver,
+    commands::output::{OutputFormat, Table},
+};
+
+use super::read_manifest;
+
+#[derive(Debug, Args)]
+pub struct ListItemArgs {
+    source: PathBuf,
+    #[arg(short = 'o', long = "output", value_enum, default_value_t)]
+    output: OutputFormat,
+    #[arg(short = 'l', long = "label")]
+    labels: Vec<String>,
+}
+impl Resolver for ListItemArgs {
+    type Context = ();
+
+    type Error = crate::Error;
+
+    fn resolve(self, _: Self::Context) -> Result<(), Self::Error> {
+        let manifest = read_manifest(&self.source)?;
+        let items = manifest
+            .items()
+            .iter()
+            .filter(|item| {
+                self.labels
+                    .iter()
+                    .all(|label| item.labels().contains(label))
+            })
+            .collect::<Vec<_>>();
+        match self.output {
+            OutputFormat::Table => {
+                let mut table = Table::new(vec!["ID", "NAME", "ENCRYPTION", "VALUE", "LABELS"]);
+                for item in items {
+                    table.row(vec![
+                        item.id().to_string(),
+                        item.name().to_string(),
+                        item.encryption().to_string(),
+                        match item.value() {
+                            ValueAccessor::None => String::new(),
+                            ValueAccessor::Path(path) => path.display().to_string(),
+                            ValueAccessor::Remote(remote) => remote.url().to_string(),
+                        },
+                        item.labels().join(","),
+                    ]);
+                }
+                table.print();
+            }
+            OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&items)?),
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,5 @@
+pub mod add;
+mod item;
+pub mod ls;
+pub mod rm;
+pub use item::*;
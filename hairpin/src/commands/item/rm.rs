@@ -0,0 +1,54 @@
+use std::path::{Component, Path, PathBuf};
+
+use clap::Args;
+use manifest::ValueAccessor;
+
+use crate::Resolver;
+
+use super::{Error, read_manifest, write_manifest};
+
+#[derive(Debug, Args)]
+pub struct RemoveItemArgs {
+    source: PathBuf,
+    /// Item ids or names
+    #[arg(required = true)]
+    items: Vec<String>,
+    #[arg(long = "keep-value")]
+    keep_value: bool,
+}
+impl Resolver for RemoveItemArgs {
+    type Context = ();
+
+    type Error = Error;
+
+    fn resolve(self, _: Self::Context) -> Result<(), Self::Error> {
+        let mut manifest = read_manifest(&self.source)?;
+        let removed = self
+            .items
+            .into_iter()
+            .map(|key| manifest.remove_item(&key).ok_or(Error::ItemNotFound(key)))
+            .collect::<Result<Vec<_>, _>>()?;
+        write_manifest(&self.source, &manifest)?;
+        if self.keep_value {
+            return Ok(());
+        }
+        for item in removed {
+            if let ValueAccessor::Path(path) = item.value()
+                && is_contained(path)
+            {
+                match std::fs::remove_file(self.source.join(path)) {
+                    Err(err) if err.kind() != std::io::ErrorKind::NotFound => {
+                        return Err(err.into());
+                    }
+                    _ => {}
+                }
+            }
+        }
+        Ok(())
+    }
+}
+/// Only values stored inside the source directory are deleted.
+fn is_contained(path: &Path) -> bool {
+    path.components()
+        .all(|component| matches!(component, Component::Normal(_) | Component::CurDir))
+}
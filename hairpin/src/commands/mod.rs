@@ -1,4 +1,14 @@
 mod commands;
 pub use commands::*;
+pub mod audit;
+pub mod config;
+pub mod connect;
 pub mod create;
+pub mod exec;
+pub mod item;
+pub mod output;
+pub mod passphrase;
+pub mod policy;
+pub mod source;
 pub mod start;
+pub mod watch;
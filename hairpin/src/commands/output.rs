@@ -0,0 +1,50 @@
+use clap::ValueEnum;
+
+#[derive(Debug, Clone, Copy, Default, ValueEnum)]
+pub enum OutputFormat {
+    #[default]
+    Table,
+    Json,
+}
+#[derive(Debug, Default)]
+pub struct Table {
+    headers: Vec<&'static str>,
+    rows: Vec<Vec<String>>,
+}
+impl Table {
+    pub fn new(headers: Vec<&'static str>) -> Self {
+        Self {
+            headers,
+            rows: Vec::new(),
+        }
+    }
+    pub fn row(&mut self, row: Vec<String>) {
+        self.rows.push(row);
+    }
+    pub fn print(&self) {
+        let mut widths = self
+            .headers
+            .iter()
+            .map(|header| header.len())
+            .collect::<Vec<_>>();
+        for row in &self.rows {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(cell.len());
+            }
+        }
+        let line = |cells: Vec<&str>| {
+            cells
+                .iter()
+                .zip(&widths)
+                .map(|(cell, width)| format!("{cell:<width$}"))
+                .collect::<Vec<_>>()
+                .join("  ")
+                .trim_end()
+                .to_string()
+        };
+        println!("{}", line(self.headers.clone()));
+        for row in &self.rows {
+            println!("{}", line(row.iter().map(String::as_str).collect()));
+        }
+    }
+}
@@ -0,0 +1,24 @@
+use std::{io::ErrorKind, path::Path};
+
+use zeroize::Zeroizing;
+
+/// Reads a passphrase from a file, or prompts on the terminal when no file is given.
+pub fn read_passphrase(file: Option<&Path>, confirm: bool) -> std::io::Result<Zeroizing<String>> {
+    if let Some(file) = file {
+        let contents = Zeroizing::new(std::fs::read_to_string(file)?);
+        return Ok(Zeroizing::new(
+            contents.trim_end_matches(['\r', '\n']).to_string(),
+        ));
+    }
+    let passphrase = Zeroizing::new(rpassword::prompt_password("Passphrase: ")?);
+    if confirm {
+        let confirmation = Zeroizing::new(rpassword::prompt_password("Confirm passphrase: ")?);
+        if passphrase != confirmation {
+            return Err(std::io::Error::new(
+                ErrorKind::InvalidInput,
+                "Passphrases do not match",
+            ));
+        }
+    }
+    Ok(passphrase)
+}
@@ -0,0 +1,65 @@
+use clap::Args;
+use hairpin_daemon::service::proto::{
+    ExplainRequest, Subject, hairpin_policy_service_client::HairpinPolicyServiceClient,
+};
+
+use crate::{
+    Resolver,
+    commands::connect::{ConnectArgs, runtime},
+};
+
+#[derive(Debug, Args)]
+pub struct ExplainPolicyArgs {
+    #[command(flatten)]
+    connect: ConnectArgs,
+    /// One of create, delete, list, unlock, read or explain
+    #[arg(short = 'a', long = "action")]
+    action: String,
+    #[arg(short = 's', long = "source")]
+    source: u64,
+    /// Item id or name, for reads
+    #[arg(short = 'i', long = "item")]
+    item: Option<String>,
+    /// Explains for this subject instead of the calling process
+    #[arg(long = "uid")]
+    uid: Option<u32>,
+    #[arg(long = "gid")]
+    gid: Option<u32>,
+    #[arg(long = "unit")]
+    unit: Option<String>,
+    #[arg(long = "exe")]
+    exe: Option<String>,
+}
+impl Resolver for ExplainPolicyArgs {
+    type Context = ();
+
+    type Error = crate::Error;
+
+    fn resolve(self, _: Self::Context) -> Result<(), Self::Error> {
+        let subject =
+            (self.uid.is_some() || self.gid.is_some() || self.unit.is_some() || self.exe.is_some())
+                .then_some(Subject {
+                    uid: self.uid,
+                    gid: self.gid,
+                    unit: self.unit,
+                    exe: self.exe,
+                });
+        let request = ExplainRequest {
+            action: self.action,
+            source_id: self.source,
+            item: self.item,
+            subject,
+        };
+        let response = runtime()?.block_on(async {
+            let mut client = HairpinPolicyServiceClient::new(self.connect.connect().await?);
+            Ok::<_, crate::Error>(client.explain(request).await?.into_inner())
+        })?;
+        let rule = match (response.rule, response.rule_name.as_str()) {
+            (Some(_), name) if !name.is_empty() => format!("rule {name:?}"),
+            (Some(index), _) => format!("rule {}", index + 1),
+            (None, _) => "no matching rule".to_string(),
+        };
+        println!("{}: {} by {rule}", response.caller, response.effect);
+        Ok(())
+    }
+}
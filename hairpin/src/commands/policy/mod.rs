@@ -0,0 +1,3 @@
+pub mod explain;
+mod policy;
+pub use policy::*;
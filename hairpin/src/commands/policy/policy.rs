@@ -0,0 +1,23 @@
+use clap::Subcommand;
+
+use crate::Resolver;
+
+use super::explain::ExplainPolicyArgs;
+
+#[derive(Debug, Subcommand)]
+pub enum PolicyCommands {
+    /// Shows whether the daemon's policy allows an action, and which rule decides it
+    #[command(arg_required_else_help = true)]
+    Explain(ExplainPolicyArgs),
+}
+impl Resolver for PolicyCommands {
+    type Context = ();
+
+    type Error = crate::Error;
+
+    fn resolve(self, context: Self::Context) -> Result<(), Self::Error> {
+        match self {
+            PolicyCommands::Explain(value) => value.resolve(context),
+        }
+    }
+}
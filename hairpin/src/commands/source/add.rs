@@ -0,0 +1,80 @@
+use std::path::Path;
+
+use clap::Args;
+use hairpin_daemon::{
+    model::HairpinSourceLocation,
+    service::proto::{
+        CreateSourceRequest, hairpin_source_service_client::HairpinSourceServiceClient,
+    },
+};
+
+use crate::{
+    Resolver,
+    commands::{
+        connect::{ConnectArgs, runtime},
+        output::{OutputFormat, Table},
+    },
+};
+
+#[derive(Debug, Args)]
+pub struct AddSourceArgs {
+    #[command(flatten)]
+    connect: ConnectArgs,
+    #[arg(short = 'o', long = "output", value_enum, default_value_t)]
+    output: OutputFormat,
+    #[arg(required = true)]
+    sources: Vec<String>,
+}
+impl Resolver for AddSourceArgs {
+    type Context = ();
+
+    type Error = crate::Error;
+
+    fn resolve(self, _: Self::Context) -> Result<(), Self::Error> {
+        let sources = self
+            .sources
+            .iter()
+            .map(String::as_str)
+            .map(to_uri)
+            .collect::<Result<Vec<_>, _>>()?;
+        let response = runtime()?.block_on(async {
+            let mut client = HairpinSourceServiceClient::new(self.connect.connect().await?);
+            Ok::<_, crate::Error>(
+                client
+                    .create(CreateSourceRequest {
+                        sources: sources.clone(),
+                    })
+                    .await?
+                    .into_inner(),
+            )
+        })?;
+        match self.output {
+            OutputFormat::Table => {
+                let mut table = Table::new(vec!["ID", "SOURCE"]);
+                for (id, source) in response.ids.iter().zip(&sources) {
+                    table.row(vec![id.to_string(), source.clone()]);
+                }
+                table.print();
+            }
+            OutputFormat::Json => {
+                let output = response
+                    .ids
+                    .iter()
+                    .zip(&sources)
+                    .map(|(id, source)| serde_json::json!({ "id": id, "source": source }))
+                    .collect::<Vec<_>>();
+                println!("{}", serde_json::to_string_pretty(&output)?);
+            }
+        }
+        Ok(())
+    }
+}
+/// Plain paths are sent to the daemon as escaped `file://localhost/...` uris.
+fn to_uri(source: &str) -> Result<String, crate::Error> {
+    if source.contains("://") {
+        Ok(source.to_string())
+    } else {
+        let path = Path::new(source).canonicalize()?;
+        Ok(HairpinSourceLocation::Local(path).to_string())
+    }
+}
@@ -0,0 +1,63 @@
+use std::path::{Path, PathBuf};
+
+use clap::Args;
+use manifest::{Diagnostic, Manifest};
+
+use crate::Resolver;
+
+#[derive(Debug, Args)]
+pub struct CheckSourceArgs {
+    /// Source directory or manifest file
+    source: PathBuf,
+}
+impl Resolver for CheckSourceArgs {
+    type Context = ();
+
+    type Error = crate::Error;
+
+    fn resolve(self, _: Self::Context) -> Result<(), Self::Error> {
+        let manifest_file = if self.source.is_dir() {
+            self.source.join(Manifest::NAME)
+        } else {
+            self.source.clone()
+        };
+        let document = std::fs::read_to_string(&manifest_file)?;
+        let root = manifest_file.parent().unwrap_or(Path::new("."));
+        let diagnostics = Manifest::validate(&document, Some(root));
+        for diagnostic in &diagnostics {
+            report(&manifest_file, &document, diagnostic);
+        }
+        let errors = diagnostics.iter().filter(|value| value.is_error()).count();
+        println!(
+            "{}: {errors} error(s), {} warning(s)",
+            manifest_file.display(),
+            diagnostics.len() - errors
+        );
+        if errors == 0 {
+            Ok(())
+        } else {
+            Err(crate::Error::CheckFailed(errors))
+        }
+    }
+}
+fn report(file: &Path, document: &str, diagnostic: &Diagnostic) {
+    let Some((line, column)) = diagnostic.location(document) else {
+        println!("{}: {diagnostic}", file.display());
+        return;
+    };
+    println!("{}:{line}:{column}: {diagnostic}", file.display());
+    let text = document.lines().nth(line - 1).unwrap_or_default();
+    let width = diagnostic
+        .span()
+        .map_or(1, |span| span.len())
+        .clamp(1, text.chars().count().saturating_sub(column - 1).max(1));
+    let gutter = line.to_string().len();
+    println!("{:gutter$} |", "");
+    println!("{line} | {text}");
+    println!(
+        "{:gutter$} | {}{}",
+        "",
+        " ".repeat(column - 1),
+        "^".repeat(width)
+    );
+}
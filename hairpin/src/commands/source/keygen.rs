@@ -0,0 +1,33 @@
+use std::{io::Write, os::unix::fs::OpenOptionsExt, path::PathBuf};
+
+use clap::Args;
+use manifest::signature;
+
+use crate::Resolver;
+
+#[derive(Debug, Args)]
+pub struct KeygenSourceArgs {
+    /// Where to write the signing key; the public key is written next to it with a `.pub` suffix
+    output: PathBuf,
+}
+impl Resolver for KeygenSourceArgs {
+    type Context = ();
+
+    type Error = crate::Error;
+
+    fn resolve(self, _: Self::Context) -> Result<(), Self::Error> {
+        let key = signature::generate();
+        let public = signature::encode(key.verifying_key().as_bytes());
+        let mut public_file = self.output.clone().into_os_string();
+        public_file.push(".pub");
+        std::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .mode(0o600)
+            .open(&self.output)?
+            .write_all(format!("{}\n", signature::encode(&key.to_bytes())).as_bytes())?;
+        std::fs::write(&public_file, format!("{public}\n"))?;
+        println!("{public}");
+        Ok(())
+    }
+}
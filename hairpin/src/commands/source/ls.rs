@@ -0,0 +1,104 @@
+use clap::Args;
+use hairpin_daemon::service::proto::{
+    FilterScalarString, FilterVectorString, ListSourceRequest, ListSourceResponse, ManifestSource,
+    hairpin_source_service_client::HairpinSourceServiceClient,
+};
+use prost_types::FieldMask;
+
+use crate::{
+    Resolver,
+    commands::{
+        connect::{ConnectArgs, runtime},
+        output::{OutputFormat, Table},
+    },
+};
+
+#[derive(Debug, Args)]
+pub struct ListSourceArgs {
+    #[command(flatten)]
+    connect: ConnectArgs,
+    #[arg(short = 'o', long = "output", value_enum, default_value_t)]
+    output: OutputFormat,
+    #[arg(short = 'n', long = "name")]
+    names: Vec<String>,
+    #[arg(short = 'l', long = "label")]
+    labels: Vec<String>,
+    #[arg(long = "exclude-label")]
+    exclude_labels: Vec<String>,
+}
+impl Resolver for ListSourceArgs {
+    type Context = ();
+
+    type Error = crate::Error;
+
+    fn resolve(self, _: Self::Context) -> Result<(), Self::Error> {
+        let request = ListSourceRequest {
+            mask: Some(FieldMask {
+                paths: ["id", "name", "version", "labels"]
+                    .map(String::from)
+                    .to_vec(),
+            }),
+            name: Some(FilterScalarString {
+                include: self.names,
+                exclude: Vec::new(),
+            }),
+            labels: Some(FilterVectorString {
+                include: self.labels,
+                exclude: self.exclude_labels,
+                include_exact: false,
+            }),
+            ..Default::default()
+        };
+        let sources = runtime()?.block_on(async {
+            let mut client = HairpinSourceServiceClient::new(self.connect.connect().await?);
+            let mut stream = client.list(request).await?.into_inner();
+            let mut sources = Vec::new();
+            while let Some(source) = stream.message().await? {
+                sources.push(source);
+            }
+            Ok::<_, crate::Error>(sources)
+        })?;
+        match self.output {
+            OutputFormat::Table => {
+                let mut table = Table::new(vec!["ID", "NAME", "VERSION", "ORIGIN", "LABELS"]);
+                for source in &sources {
+                    let manifest = source.manifest.clone().unwrap_or_default();
+                    table.row(vec![
+                        source.id.to_string(),
+                        manifest.name,
+                        manifest.version,
+                        origin(source).to_string(),
+                        manifest.labels.join(","),
+                    ]);
+                }
+                table.print();
+            }
+            OutputFormat::Json => {
+                let output = sources
+                    .iter()
+                    .map(|source| {
+                        let manifest = source.manifest.clone().unwrap_or_default();
+                        serde_json::json!({
+                            "id": source.id,
+                            "manifest": manifest.id,
+                            "name": manifest.name,
+                            "version": manifest.version,
+                            "origin": origin(source),
+                            "labels": manifest.labels,
+                        })
+                    })
+                    .collect::<Vec<_>>();
+                println!("{}", serde_json::to_string_pretty(&output)?);
+            }
+        }
+        Ok(())
+    }
+}
+fn origin(source: &ListSourceResponse) -> &'static str {
+    match source.source() {
+        ManifestSource::Unknown => "unknown",
+        ManifestSource::InternalDisk => "internal-disk",
+        ManifestSource::ExternalDisk => "external-disk",
+        ManifestSource::Remote => "remote",
+    }
+}
@@ -0,0 +1,9 @@
+pub mod add;
+pub mod check;
+pub mod keygen;
+pub mod ls;
+pub mod rm;
+pub mod sign;
+mod source;
+pub mod unlock;
+pub use source::*;
@@ -0,0 +1,30 @@
+use clap::Args;
+use hairpin_daemon::service::proto::{
+    DeleteSourceRequest, hairpin_source_service_client::HairpinSourceServiceClient,
+};
+
+use crate::{
+    Resolver,
+    commands::connect::{ConnectArgs, runtime},
+};
+
+#[derive(Debug, Args)]
+pub struct RemoveSourceArgs {
+    #[command(flatten)]
+    connect: ConnectArgs,
+    #[arg(required = true)]
+    ids: Vec<u64>,
+}
+impl Resolver for RemoveSourceArgs {
+    type Context = ();
+
+    type Error = crate::Error;
+
+    fn resolve(self, _: Self::Context) -> Result<(), Self::Error> {
+        runtime()?.block_on(async {
+            let mut client = HairpinSourceServiceClient::new(self.connect.connect().await?);
+            client.delete(DeleteSourceRequest { ids: self.ids }).await?;
+            Ok(())
+        })
+    }
+}
@@ -0,0 +1,43 @@
+use std::path::PathBuf;
+
+use clap::Args;
+use manifest::{Diagnostic, Manifest, path::manifest_file, signature};
+
+use crate::Resolver;
+
+#[derive(Debug, Args)]
+pub struct SignSourceArgs {
+    /// Source directory or manifest file
+    source: PathBuf,
+    /// File holding the hex encoded Ed25519 signing key
+    #[arg(short = 'k', long = "key")]
+    key: PathBuf,
+}
+impl Resolver for SignSourceArgs {
+    type Context = ();
+
+    type Error = crate::Error;
+
+    fn resolve(self, _: Self::Context) -> Result<(), Self::Error> {
+        let manifest_file = manifest_file(&self.source);
+        let document = std::fs::read_to_string(&manifest_file)?;
+        let errors = Manifest::validate(&document, manifest_file.parent())
+            .into_iter()
+            .filter(Diagnostic::is_error)
+            .count();
+        if errors > 0 {
+            return Err(crate::Error::CheckFailed(errors));
+        }
+        let manifest = toml::from_str::<Manifest>(&document)?;
+        let key = signature::parse_signing_key(&std::fs::read_to_string(&self.key)?)?;
+        let mut signature_file = manifest_file.clone().into_os_string();
+        signature_file.push(".sig");
+        std::fs::write(&signature_file, manifest.sign(&key)? + "\n")?;
+        println!(
+            "Signed {} with {}",
+            manifest_file.display(),
+            signature::encode(key.verifying_key().as_bytes())
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,44 @@
+use clap::Subcommand;
+
+use crate::Resolver;
+
+use super::{
+    add::AddSourceArgs, check::CheckSourceArgs, keygen::KeygenSourceArgs, ls::ListSourceArgs,
+    rm::RemoveSourceArgs, sign::SignSourceArgs, unlock::UnlockSourceArgs,
+};
+
+#[derive(Debug, Subcommand)]
+pub enum SourceCommands {
+    #[command(arg_required_else_help = true)]
+    Add(AddSourceArgs),
+    #[command(arg_required_else_help = true)]
+    Rm(RemoveSourceArgs),
+    Ls(ListSourceArgs),
+    #[command(arg_required_else_help = true)]
+    Unlock(UnlockSourceArgs),
+    #[command(arg_required_else_help = true)]
+    Check(CheckSourceArgs),
+    /// Writes a detached signature of the manifest for daemons that require trusted sources
+    #[command(arg_required_else_help = true)]
+    Sign(SignSourceArgs),
+    /// Generates an Ed25519 key pair for signing sources
+    #[command(arg_required_else_help = true)]
+    Keygen(KeygenSourceArgs),
+}
+impl Resolver for SourceCommands {
+    type Context = ();
+
+    type Error = crate::Error;
+
+    fn resolve(self, context: Self::Context) -> Result<(), Self::Error> {
+        match self {
+            SourceCommands::Add(value) => value.resolve(context),
+            SourceCommands::Rm(value) => value.resolve(context),
+            SourceCommands::Ls(value) => value.resolve(context),
+            SourceCommands::Unlock(value) => value.resolve(context),
+            SourceCommands::Check(value) => value.resolve(context),
+            SourceCommands::Sign(value) => value.resolve(context),
+            SourceCommands::Keygen(value) => value.resolve(context),
+        }
+    }
+}
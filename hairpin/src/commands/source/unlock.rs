@@ -0,0 +1,42 @@
+use std::path::PathBuf;
+
+use clap::Args;
+use hairpin_daemon::service::proto::{
+    UnlockSourceRequest, hairpin_source_service_client::HairpinSourceServiceClient,
+};
+
+use crate::{
+    Resolver,
+    commands::{
+        connect::{ConnectArgs, runtime},
+        passphrase::read_passphrase,
+    },
+};
+
+#[derive(Debug, Args)]
+pub struct UnlockSourceArgs {
+    #[command(flatten)]
+    connect: ConnectArgs,
+    id: u64,
+    #[arg(long = "passphrase-file")]
+    passphrase_file: Option<PathBuf>,
+}
+impl Resolver for UnlockSourceArgs {
+    type Context = ();
+
+    type Error = crate::Error;
+
+    fn resolve(self, _: Self::Context) -> Result<(), Self::Error> {
+        let passphrase = read_passphrase(self.passphrase_file.as_deref(), false)?;
+        runtime()?.block_on(async {
+            let mut client = HairpinSourceServiceClient::new(self.connect.connect().await?);
+            client
+                .unlock(UnlockSourceRequest {
+                    id: self.id,
+                    passphrase: passphrase.to_string(),
+                })
+                .await?;
+            Ok(())
+        })
+    }
+}
@@ -0,0 +1,152 @@
+use clap::Args;
+use hairpin_daemon::service::proto::{
+    FilterVectorString, ItemRef, ManifestSource, WatchSourceRequest, WatchSourceResponse,
+    hairpin_source_service_client::HairpinSourceServiceClient, watch_source_response::Event,
+};
+use serde_json::{Value, json};
+
+use crate::{
+    Resolver,
+    commands::{
+        connect::{ConnectArgs, runtime},
+        output::OutputFormat,
+    },
+};
+
+/// Prints source and delivery events as they happen, one per line
+#[derive(Debug, Args)]
+pub struct WatchArgs {
+    #[command(flatten)]
+    connect: ConnectArgs,
+    #[arg(short = 'o', long = "output", value_enum, default_value_t)]
+    output: OutputFormat,
+    /// Only reports sources with this label
+    #[arg(short = 'l', long = "label")]
+    labels: Vec<String>,
+    #[arg(long = "exclude-label")]
+    exclude_labels: Vec<String>,
+}
+impl Resolver for WatchArgs {
+    type Context = ();
+
+    type Error = crate::Error;
+
+    fn resolve(self, _: Self::Context) -> Result<(), Self::Error> {
+        let request = WatchSourceRequest {
+            labels: Some(FilterVectorString {
+                include: self.labels,
+                exclude: self.exclude_labels,
+                include_exact: false,
+            }),
+        };
+        runtime()?.block_on(async {
+            let mut client = HairpinSourceServiceClient::new(self.connect.connect().await?);
+            let mut stream = client.watch(request).await?.into_inner();
+            while let Some(response) = stream.message().await? {
+                match self.output {
+                    OutputFormat::Table => println!("{}", line(&response)),
+                    OutputFormat::Json => {
+                        println!("{}", serde_json::to_string(&object(&response))?)
+                    }
+                }
+            }
+            Ok::<_, crate::Error>(())
+        })
+    }
+}
+fn time(response: &WatchSourceResponse) -> String {
+    response
+        .time
+        .map(|time| time.to_string())
+        .unwrap_or_default()
+}
+fn origin(source: ManifestSource) -> &'static str {
+    match source {
+        ManifestSource::Unknown => "unknown",
+        ManifestSource::InternalDisk => "internal-disk",
+        ManifestSource::ExternalDisk => "external-disk",
+        ManifestSource::Remote => "remote",
+    }
+}
+fn names(items: &[ItemRef]) -> String {
+    items
+        .iter()
+        .map(|item| item.name.as_str())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+fn line(response: &WatchSourceResponse) -> String {
+    let (kind, detail) = match &response.event {
+        Some(Event::SourceAdded(event)) => (
+            "source-added",
+            format!("{} ({})", event.location, origin(event.source())),
+        ),
+        Some(Event::SourceRemoved(event)) => ("source-removed", event.location.clone()),
+        Some(Event::ManifestChanged(event)) => (
+            "manifest-changed",
+            format!(
+                "version {} added [{}] removed [{}] changed [{}]",
+                event.version,
+                names(&event.added),
+                names(&event.removed),
+                names(&event.changed)
+            ),
+        ),
+        Some(Event::DeliveryMaterialized(event)) => ("delivery-materialized", event.path.clone()),
+        Some(Event::DeliveryRevoked(event)) => ("delivery-revoked", event.path.clone()),
+        None => ("unknown", String::new()),
+    };
+    format!(
+        "{}  {}  {}  {kind}  {detail}",
+        time(response),
+        response.id,
+        response.name
+    )
+}
+fn object(response: &WatchSourceResponse) -> Value {
+    let items = |items: &[ItemRef]| {
+        items
+            .iter()
+            .map(|item| json!({ "id": item.id, "name": item.name }))
+            .collect::<Vec<_>>()
+    };
+    let (kind, detail) = match &response.event {
+        Some(Event::SourceAdded(event)) => (
+            "source-added",
+            json!({ "location": event.location, "origin": origin(event.source()) }),
+        ),
+        Some(Event::SourceRemoved(event)) => {
+            ("source-removed", json!({ "location": event.location }))
+        }
+        Some(Event::ManifestChanged(event)) => (
+            "manifest-changed",
+            json!({
+                "version": event.version,
+                "added": items(&event.added),
+                "removed": items(&event.removed),
+                "changed": items(&event.changed),
+            }),
+        ),
+        Some(Event::DeliveryMaterialized(event)) => (
+            "delivery-materialized",
+            json!({ "target": event.name, "path": event.path, "item": event.item }),
+        ),
+        Some(Event::DeliveryRevoked(event)) => (
+            "delivery-revoked",
+            json!({ "target": event.name, "path": event.path }),
+        ),
+        None => ("unknown", json!({})),
+    };
+    let mut object = json!({
+        "time": time(response),
+        "id": response.id,
+        "manifest": response.manifest_id,
+        "name": response.name,
+        "labels": response.labels,
+        "event": kind,
+    });
+    if let (Value::Object(object), Value::Object(detail)) = (&mut object, detail) {
+        object.extend(detail);
+    }
+    object
+}
@@ -11,6 +11,29 @@ pub enum Error {
     Clap(#[from] clap::Error),
     #[error(transparent)]
     InvalidCreateSourceArgs(#[from] commands::create::source::Error),
+    #[error(transparent)]
+    InvalidItemArgs(#[from] commands::item::Error),
+    #[error(transparent)]
+    Transport(#[from] tonic::transport::Error),
+    #[error("{}", .0.message())]
+    Status(Box<tonic::Status>),
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    #[error(transparent)]
+    InvalidManifest(#[from] toml::de::Error),
+    #[error(transparent)]
+    Signature(#[from] manifest::signature::Error),
+    #[error("Value for {0} contains a NUL byte and can't be placed in the environment")]
+    InvalidVariable(String),
+    #[error("{0} isn't a valid environment variable name")]
+    InvalidVariableName(String),
+    #[error("{0} error(s) found")]
+    CheckFailed(usize),
     #[error("Undefined")]
     Undefined,
 }
+impl From<tonic::Status> for Error {
+    fn from(value: tonic::Status) -> Self {
+        Self::Status(Box::new(value))
+    }
+}
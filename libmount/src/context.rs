@@ -1,23 +1,45 @@
 use std::{
-    ffi::{CStr, CString},
+    ffi::{CStr, CString, c_char, c_int, c_ulong},
+    ops::BitOr,
     os::unix::ffi::OsStrExt,
     path::Path,
-    ptr::null,
-    str::FromStr,
 };
 
 use crate::{
     error::{AllocationError, Error},
     libmount::root::{
-        libmnt_context, mnt_free_context, mnt_new_context, mnt_table_parse_file,
-        mnt_table_parse_mtab,
+        libmnt_context, mnt_context_append_options, mnt_context_disable_mtab,
+        mnt_context_get_excode, mnt_context_get_status, mnt_context_get_syscall_errno,
+        mnt_context_mount, mnt_context_set_fstype, mnt_context_set_mflags, mnt_context_set_options,
+        mnt_context_set_source, mnt_context_set_target, mnt_context_umount, mnt_free_context,
+        mnt_new_context,
     },
-    table::Table,
-    util::get_utab_path,
 };
 
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MountFlags(c_ulong);
+impl MountFlags {
+    pub const RDONLY: Self = Self(libc::MS_RDONLY);
+    pub const NOSUID: Self = Self(libc::MS_NOSUID);
+    pub const NODEV: Self = Self(libc::MS_NODEV);
+    pub const NOEXEC: Self = Self(libc::MS_NOEXEC);
+    pub const NOATIME: Self = Self(libc::MS_NOATIME);
+    pub const SILENT: Self = Self(libc::MS_SILENT);
+    pub fn bits(&self) -> c_ulong {
+        self.0
+    }
+}
+impl BitOr for MountFlags {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self::Output {
+        Self(self.0 | rhs.0)
+    }
+}
+
 #[derive(Debug)]
 pub struct Context(*mut libmnt_context);
+unsafe impl Send for Context {}
 impl Context {
     pub fn new() -> Result<Self, AllocationError<Self>> {
         unsafe {
@@ -29,6 +51,95 @@ impl Context {
             }
         }
     }
+    pub fn set_source(&mut self, source: impl AsRef<Path>) -> Result<&mut Self, Error> {
+        let source = path_to_cstring(source.as_ref())?;
+        self.set("source", |cxt| unsafe {
+            mnt_context_set_source(cxt, source.as_ptr())
+        })
+    }
+    pub fn set_target(&mut self, target: impl AsRef<Path>) -> Result<&mut Self, Error> {
+        let target = path_to_cstring(target.as_ref())?;
+        self.set("target", |cxt| unsafe {
+            mnt_context_set_target(cxt, target.as_ptr())
+        })
+    }
+    pub fn set_fstype(&mut self, fstype: &str) -> Result<&mut Self, Error> {
+        let fstype = CString::new(fstype)?;
+        self.set("fstype", |cxt| unsafe {
+            mnt_context_set_fstype(cxt, fstype.as_ptr())
+        })
+    }
+    /// Replaces the comma separated mount options.
+    pub fn set_options(&mut self, options: &str) -> Result<&mut Self, Error> {
+        let options = CString::new(options)?;
+        self.set("options", |cxt| unsafe {
+            mnt_context_set_options(cxt, options.as_ptr())
+        })
+    }
+    pub fn append_options(&mut self, options: &str) -> Result<&mut Self, Error> {
+        let options = CString::new(options)?;
+        self.set("options", |cxt| unsafe {
+            mnt_context_append_options(cxt, options.as_ptr())
+        })
+    }
+    pub fn set_mount_flags(&mut self, flags: MountFlags) -> Result<&mut Self, Error> {
+        self.set("mount flags", |cxt| unsafe {
+            mnt_context_set_mflags(cxt, flags.bits())
+        })
+    }
+    /// Skips updating the userspace mount table, required inside unprivileged namespaces.
+    pub fn disable_mtab(&mut self, disable: bool) -> Result<&mut Self, Error> {
+        self.set("mtab", |cxt| unsafe {
+            mnt_context_disable_mtab(cxt, disable as c_int)
+        })
+    }
+    pub fn mount(&mut self) -> Result<(), Error> {
+        let result = unsafe { mnt_context_mount(self.0) };
+        self.status(result)
+            .map_err(|(errno, message)| Error::Mount {
+                code: result,
+                errno,
+                message,
+            })
+    }
+    pub fn umount(&mut self) -> Result<(), Error> {
+        let result = unsafe { mnt_context_umount(self.0) };
+        self.status(result)
+            .map_err(|(errno, message)| Error::Umount {
+                code: result,
+                errno,
+                message,
+            })
+    }
+    fn set(
+        &mut self,
+        name: &'static str,
+        f: impl FnOnce(*mut libmnt_context) -> c_int,
+    ) -> Result<&mut Self, Error> {
+        match f(self.0) {
+            0 => Ok(self),
+            result => Err(Error::Context(name, result)),
+        }
+    }
+    fn status(&self, result: c_int) -> Result<(), (i32, String)> {
+        unsafe {
+            if result == 0 && mnt_context_get_status(self.0) == 1 {
+                return Ok(());
+            }
+            let errno = mnt_context_get_syscall_errno(self.0);
+            let mut buffer = [0 as c_char; 512];
+            mnt_context_get_excode(self.0, result, buffer.as_mut_ptr(), buffer.len());
+            let message = CStr::from_ptr(buffer.as_ptr()).to_string_lossy();
+            let message = if !message.is_empty() {
+                message.into_owned()
+            } else if errno != 0 {
+                std::io::Error::from_raw_os_error(errno).to_string()
+            } else {
+                format!("libmount error {result}")
+            };
+            Err((errno, message))
+        }
+    }
 }
 impl Drop for Context {
     fn drop(&mut self) {
@@ -37,3 +148,6 @@ impl Drop for Context {
         }
     }
 }
+fn path_to_cstring(path: &Path) -> Result<CString, Error> {
+    Ok(CString::new(path.as_os_str().as_bytes())?)
+}
@@ -66,6 +66,10 @@ pub enum Error {
     MonitorPoll(i32),
     #[error("Error monitoring next change: {0}")]
     MonitorNextChange(i32),
+    #[error("Error getting monitor file descriptor: {0}")]
+    MonitorFd(i32),
+    #[error(transparent)]
+    IO(#[from] std::io::Error),
     #[error("Undefined Monitor Type {0}")]
     UndefinedMonitorType(u32),
     #[error("Undefined Direction {0}")]
@@ -75,6 +79,23 @@ pub enum Error {
 
     #[error("Error in iteration {0}")]
     Iter(i32),
+
+    #[error("Error setting context {0}: {1}")]
+    Context(&'static str, i32),
+    #[error("Invalid context string: {0}")]
+    ContextString(#[from] std::ffi::NulError),
+    #[error("Error mounting ({code}, errno {errno}): {message}")]
+    Mount {
+        code: i32,
+        errno: i32,
+        message: String,
+    },
+    #[error("Error unmounting ({code}, errno {errno}): {message}")]
+    Umount {
+        code: i32,
+        errno: i32,
+        message: String,
+    },
 }
 #[derive(Debug, thiserror::Error)]
 pub enum ServeError<E>
@@ -1,35 +1,61 @@
-use std::{ops::BitOr, path::Path};
+use std::{borrow::Cow, ops::BitOr, path::Path};
 
-use crate::{error::Error, fs::FileSystem, monitor::MonitorType};
+use crate::{error::Error, fs::FileSystemInfo, monitor::MonitorType};
 #[derive(Debug)]
 pub enum Event<'a> {
     MountEvent(MountEvent<'a>),
     Close,
     Error(Error),
 }
+/// Mount table an event was derived from. Each table is only diffed against its own previous
+/// snapshot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum MountTable {
+    /// The kernel mount table, `/proc/self/mountinfo`.
+    Kernel,
+    /// Userspace mount options, `/run/mount/utab`.
+    Userspace,
+    /// Configured filesystems, `/etc/fstab`. Its events describe edits, not mounts.
+    Fstab,
+}
+impl From<MonitorType> for MountTable {
+    fn from(value: MonitorType) -> Self {
+        match value {
+            MonitorType::Kernel => MountTable::Kernel,
+            MonitorType::Userspace => MountTable::Userspace,
+        }
+    }
+}
+/// A change to a mount table. Filesystems are owned copies, so events can be sent across tasks
+/// without sharing libmount objects.
 #[derive(Debug, Clone)]
 pub enum MountEvent<'a> {
     MonitorUpdate {
-        location: &'a Path,
+        location: Cow<'a, Path>,
         monitor_type: MonitorType,
     },
 
     Mount {
-        filesystem: FileSystem,
+        filesystem: Box<FileSystemInfo>,
+        table: MountTable,
     },
     UMount {
-        filesystem: FileSystem,
+        filesystem: Box<FileSystemInfo>,
+        table: MountTable,
     },
     Remount {
-        filesystem: FileSystem,
+        filesystem: Box<FileSystemInfo>,
+        table: MountTable,
     },
     Move {
-        from: FileSystem,
-        to: FileSystem,
+        from: Box<FileSystemInfo>,
+        to: Box<FileSystemInfo>,
+        table: MountTable,
     },
     Propagate {
-        parent: FileSystem,
-        child: FileSystem,
+        parent: Box<FileSystemInfo>,
+        child: Box<FileSystemInfo>,
+        table: MountTable,
     },
 }
 #[derive(Debug, Clone, Copy)]
@@ -61,14 +87,22 @@ impl<'a> MountEvent<'a> {
                 location: _,
                 monitor_type: _,
             } => MountEventMask::MONITOR_UPDATE,
-            MountEvent::Mount { filesystem: _ } => MountEventMask::MOUNT,
-            MountEvent::UMount { filesystem: _ } => MountEventMask::UMOUNT,
-            MountEvent::Remount { filesystem: _ } => MountEventMask::REMOUNT,
-            MountEvent::Move { from: _, to: _ } => MountEventMask::MOVE,
-            MountEvent::Propagate {
-                parent: _,
-                child: _,
-            } => MountEventMask::PROPAGATE,
+            MountEvent::Mount { .. } => MountEventMask::MOUNT,
+            MountEvent::UMount { .. } => MountEventMask::UMOUNT,
+            MountEvent::Remount { .. } => MountEventMask::REMOUNT,
+            MountEvent::Move { .. } => MountEventMask::MOVE,
+            MountEvent::Propagate { .. } => MountEventMask::PROPAGATE,
+        }
+    }
+    /// The table the event was derived from, `None` for monitor updates.
+    pub fn table(&self) -> Option<MountTable> {
+        match self {
+            MountEvent::MonitorUpdate { .. } => None,
+            MountEvent::Mount { table, .. }
+            | MountEvent::UMount { table, .. }
+            | MountEvent::Remount { table, .. }
+            | MountEvent::Move { table, .. }
+            | MountEvent::Propagate { table, .. } => Some(*table),
         }
     }
 }
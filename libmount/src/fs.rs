@@ -1,21 +1,25 @@
 use std::{
-    ffi::{CStr, OsStr},
+    ffi::{CStr, CString, OsStr, c_char},
     os::unix::ffi::OsStrExt,
-    path::Path,
+    path::{Path, PathBuf},
+    ptr::{null, null_mut},
+    slice,
 };
 
 use crate::{
-    error::{AllocationError, Error},
+    error::AllocationError,
     libmount::root::{
-        libmnt_fs, mnt_fs_get_bindsrc, mnt_fs_get_root, mnt_fs_get_target, mnt_new_fs, mnt_ref_fs,
-        mnt_unref_fs,
+        libmnt_fs, mnt_fs_get_bindsrc, mnt_fs_get_devno, mnt_fs_get_fs_options, mnt_fs_get_fstype,
+        mnt_fs_get_id, mnt_fs_get_option, mnt_fs_get_options, mnt_fs_get_parent_id,
+        mnt_fs_get_root, mnt_fs_get_source, mnt_fs_get_srcpath, mnt_fs_get_tag, mnt_fs_get_target,
+        mnt_fs_get_user_options, mnt_fs_get_vfs_options, mnt_new_fs, mnt_ref_fs, mnt_unref_fs,
     },
 };
 
+/// A filesystem entry of a table. Its reference count isn't atomic, so it stays on the thread
+/// that read it; [`FileSystemInfo`] is what crosses threads.
 #[derive(Debug)]
 pub struct FileSystem(pub(crate) *mut libmnt_fs);
-unsafe impl Send for FileSystem {}
-unsafe impl Sync for FileSystem {}
 impl Clone for FileSystem {
     fn clone(&self) -> Self {
         unsafe {
@@ -35,41 +39,108 @@ impl FileSystem {
             }
         }
     }
-    pub fn root<'a>(&self) -> Option<&'a Path> {
+    /// Wraps a filesystem owned by a table, taking a reference so it outlives the table.
+    pub(crate) unsafe fn borrowed(value: *mut libmnt_fs) -> Self {
         unsafe {
-            let value = mnt_fs_get_root(self.0);
-            if !value.is_null() {
-                let value = CStr::from_ptr(value);
-                let value = Path::new(OsStr::from_bytes(value.to_bytes()));
-                Some(value)
-            } else {
-                None
-            }
+            mnt_ref_fs(value);
         }
+        Self(value)
     }
-    pub fn bindsrc<'a>(&self) -> Option<&'a Path> {
-        unsafe {
-            let value = mnt_fs_get_bindsrc(self.0);
-            if !value.is_null() {
-                let value = CStr::from_ptr(value);
-                let value = Path::new(OsStr::from_bytes(value.to_bytes()));
-                Some(value)
-            } else {
-                None
-            }
+    /// Borrows a string owned by the filesystem, which lives as long as our reference to it.
+    fn bytes(&self, value: *const c_char) -> Option<&[u8]> {
+        if !value.is_null() {
+            unsafe { Some(CStr::from_ptr(value).to_bytes()) }
+        } else {
+            None
         }
     }
-    pub fn target<'a>(&self) -> Option<&'a Path> {
-        unsafe {
-            let value = mnt_fs_get_target(self.0);
-            if !value.is_null() {
-                let value = CStr::from_ptr(value);
-                let value = Path::new(OsStr::from_bytes(value.to_bytes()));
-                Some(value)
-            } else {
-                None
-            }
+    fn path(&self, value: *const c_char) -> Option<&Path> {
+        self.bytes(value)
+            .map(|value| Path::new(OsStr::from_bytes(value)))
+    }
+    fn str(&self, value: *const c_char) -> Option<&str> {
+        self.bytes(value)
+            .and_then(|value| std::str::from_utf8(value).ok())
+    }
+    pub fn root(&self) -> Option<&Path> {
+        self.path(unsafe { mnt_fs_get_root(self.0) })
+    }
+    pub fn bindsrc(&self) -> Option<&Path> {
+        self.path(unsafe { mnt_fs_get_bindsrc(self.0) })
+    }
+    pub fn target(&self) -> Option<&Path> {
+        self.path(unsafe { mnt_fs_get_target(self.0) })
+    }
+    /// Source as written in the table, a device path, a tag such as `UUID=...` or anything the
+    /// filesystem accepts, like `tmpfs`.
+    pub fn source(&self) -> Option<&str> {
+        self.str(unsafe { mnt_fs_get_source(self.0) })
+    }
+    /// Source path, `None` for tags and pseudo filesystems.
+    pub fn srcpath(&self) -> Option<&Path> {
+        self.path(unsafe { mnt_fs_get_srcpath(self.0) })
+    }
+    pub fn fstype(&self) -> Option<&str> {
+        self.str(unsafe { mnt_fs_get_fstype(self.0) })
+    }
+    /// All options, VFS, filesystem specific and userspace ones alike.
+    pub fn options(&self) -> Option<&str> {
+        self.str(unsafe { mnt_fs_get_options(self.0) })
+    }
+    /// Options of the mount point, such as `ro` or `nosuid`.
+    pub fn vfs_options(&self) -> Option<&str> {
+        self.str(unsafe { mnt_fs_get_vfs_options(self.0) })
+    }
+    /// Options of the superblock, specific to the filesystem type.
+    pub fn fs_options(&self) -> Option<&str> {
+        self.str(unsafe { mnt_fs_get_fs_options(self.0) })
+    }
+    /// Options only known to userspace, kept in utab or fstab.
+    pub fn user_options(&self) -> Option<&str> {
+        self.str(unsafe { mnt_fs_get_user_options(self.0) })
+    }
+    /// Looks up an option, `Some(None)` meaning it is set without a value, as `ro` usually is.
+    pub fn get_option(&self, name: &str) -> Option<Option<&str>> {
+        let name = CString::new(name).ok()?;
+        let mut value = null_mut();
+        let mut size = 0;
+        let result = unsafe { mnt_fs_get_option(self.0, name.as_ptr(), &mut value, &mut size) };
+        if result != 0 {
+            return None;
+        }
+        if value.is_null() || size == 0 {
+            return Some(None);
         }
+        // The value points into the option string and isn't terminated after it.
+        let value = unsafe { slice::from_raw_parts(value as *const u8, size) };
+        Some(std::str::from_utf8(value).ok())
+    }
+    /// Mount id from mountinfo, `None` for filesystems from other tables.
+    pub fn id(&self) -> Option<u32> {
+        u32::try_from(unsafe { mnt_fs_get_id(self.0) })
+            .ok()
+            .filter(|id| *id > 0)
+    }
+    pub fn parent_id(&self) -> Option<u32> {
+        u32::try_from(unsafe { mnt_fs_get_parent_id(self.0) })
+            .ok()
+            .filter(|id| *id > 0)
+    }
+    /// Device number from mountinfo, `None` for filesystems from other tables.
+    pub fn devno(&self) -> Option<u64> {
+        Some(unsafe { mnt_fs_get_devno(self.0) } as u64).filter(|devno| *devno != 0)
+    }
+    /// Tag the source was given as, such as `("UUID", "...")` or `("LABEL", "...")`.
+    pub fn tag(&self) -> Option<(&str, &str)> {
+        let mut name = null();
+        let mut value = null();
+        if unsafe { mnt_fs_get_tag(self.0, &mut name, &mut value) } != 0 {
+            return None;
+        }
+        Some((self.str(name)?, self.str(value)?))
+    }
+    pub fn info(&self) -> FileSystemInfo {
+        FileSystemInfo::from(self)
     }
 }
 impl Drop for FileSystem {
@@ -79,3 +150,102 @@ impl Drop for FileSystem {
         }
     }
 }
+/// Owned copy of what a [`FileSystem`] describes, safe to keep after its table is gone.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FileSystemInfo {
+    source: Option<String>,
+    target: Option<PathBuf>,
+    root: Option<PathBuf>,
+    bindsrc: Option<PathBuf>,
+    fstype: Option<String>,
+    vfs_options: Option<String>,
+    fs_options: Option<String>,
+    user_options: Option<String>,
+    id: Option<u32>,
+    parent_id: Option<u32>,
+    devno: Option<u64>,
+    tag: Option<FileSystemTag>,
+}
+impl FileSystemInfo {
+    pub fn source(&self) -> Option<&str> {
+        self.source.as_deref()
+    }
+    pub fn target(&self) -> Option<&Path> {
+        self.target.as_deref()
+    }
+    pub fn root(&self) -> Option<&Path> {
+        self.root.as_deref()
+    }
+    pub fn bindsrc(&self) -> Option<&Path> {
+        self.bindsrc.as_deref()
+    }
+    pub fn fstype(&self) -> Option<&str> {
+        self.fstype.as_deref()
+    }
+    pub fn vfs_options(&self) -> Option<&str> {
+        self.vfs_options.as_deref()
+    }
+    pub fn fs_options(&self) -> Option<&str> {
+        self.fs_options.as_deref()
+    }
+    pub fn user_options(&self) -> Option<&str> {
+        self.user_options.as_deref()
+    }
+    pub fn id(&self) -> Option<u32> {
+        self.id
+    }
+    pub fn parent_id(&self) -> Option<u32> {
+        self.parent_id
+    }
+    pub fn devno(&self) -> Option<u64> {
+        self.devno
+    }
+    pub fn tag(&self) -> Option<&FileSystemTag> {
+        self.tag.as_ref()
+    }
+    /// Whether the mount point or the filesystem is read only.
+    pub fn read_only(&self) -> bool {
+        [&self.vfs_options, &self.fs_options]
+            .into_iter()
+            .flatten()
+            .any(|options| options.split(',').any(|option| option == "ro"))
+    }
+}
+impl From<&FileSystem> for FileSystemInfo {
+    fn from(value: &FileSystem) -> Self {
+        Self {
+            source: value.source().map(str::to_string),
+            target: value.target().map(Path::to_path_buf),
+            root: value.root().map(Path::to_path_buf),
+            bindsrc: value.bindsrc().map(Path::to_path_buf),
+            fstype: value.fstype().map(str::to_string),
+            vfs_options: value.vfs_options().map(str::to_string),
+            fs_options: value.fs_options().map(str::to_string),
+            user_options: value.user_options().map(str::to_string),
+            id: value.id(),
+            parent_id: value.parent_id(),
+            devno: value.devno(),
+            tag: value.tag().map(|(name, value)| FileSystemTag {
+                name: name.to_string(),
+                value: value.to_string(),
+            }),
+        }
+    }
+}
+/// A source given by tag, as in `UUID=...`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FileSystemTag {
+    name: String,
+    value: String,
+}
+impl FileSystemTag {
+    /// `UUID`, `LABEL`, `PARTUUID`, `PARTLABEL` or `ID`.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+}
@@ -1,30 +1,32 @@
 use std::{
-    alloc::Layout,
-    borrow::Cow,
-    collections::BTreeMap,
-    ffi::{CStr, OsStr},
-    i16,
-    os::unix::ffi::OsStrExt,
-    path::Path,
+    collections::{BTreeMap, VecDeque},
+    ffi::{CStr, CString, OsStr, OsString},
+    io::ErrorKind,
+    mem::size_of,
+    os::{
+        fd::{AsRawFd, FromRawFd, OwnedFd, RawFd},
+        unix::ffi::OsStrExt,
+    },
+    path::{Path, PathBuf},
+    pin::Pin,
     ptr::null,
+    task::Poll,
     time::Duration,
 };
 
-use libc::{c_char, pollfd};
-use tokio::sync::mpsc::UnboundedSender;
+use libc::c_char;
+use tokio::io::{Interest, unix::AsyncFd};
+use tokio_stream::{Stream, StreamExt};
 
 use crate::{
-    context::Context,
     error::{AllocationError, Error},
-    event::{Event, MountEvent},
+    event::{Event, MountEvent, MountTable},
     libmount::root::{
-        MNT_MONITOR_TYPE_KERNEL, MNT_MONITOR_TYPE_USERSPACE, libmnt_monitor, mnt_get_fstab_path,
+        MNT_MONITOR_TYPE_KERNEL, MNT_MONITOR_TYPE_USERSPACE, libmnt_monitor,
         mnt_monitor_enable_kernel, mnt_monitor_enable_userspace, mnt_monitor_get_fd,
-        mnt_monitor_next_change, mnt_monitor_wait, mnt_new_monitor, mnt_ref_monitor,
-        mnt_unref_monitor,
+        mnt_monitor_next_change, mnt_new_monitor, mnt_unref_monitor,
     },
     table::Table,
-    update::TableUpdate,
 };
 #[repr(u32)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
@@ -44,9 +46,10 @@ impl TryFrom<i32> for MonitorType {
     }
 }
 
+/// A libmount monitor, owned by one thread at a time. It isn't `Clone`, as sharing its
+/// non-atomic reference count between threads would race.
 pub struct Monitor(*mut libmnt_monitor);
 unsafe impl Send for Monitor {}
-unsafe impl Sync for Monitor {}
 impl Monitor {
     pub fn new() -> Result<Self, AllocationError<Monitor>> {
         unsafe {
@@ -81,10 +84,50 @@ impl Monitor {
             }
         }
     }
+    /// Streams mount changes, diffing each changed table against its snapshot, first read from
+    /// the file given for it in `tables`, or empty if that doesn't exist.
+    /// Tables without a snapshot report all their filesystems as mounted on their first change.
+    /// libmount doesn't monitor fstab, so a [`MountTable::Fstab`] file is watched with inotify.
+    /// With `fire_initial`, every filesystem of the snapshots is first reported as mounted.
+    /// Monitoring stops when the stream is dropped.
+    pub fn into_stream(
+        self,
+        tables: impl IntoIterator<Item = (MountTable, PathBuf)>,
+        fire_initial: bool,
+    ) -> Result<MonitorStream, Error> {
+        let fd = unsafe { mnt_monitor_get_fd(self.0) };
+        if fd < 0 {
+            return Err(Error::MonitorFd(fd));
+        }
+        let tables = tables.into_iter().collect::<Vec<_>>();
+        let snapshots = tables
+            .iter()
+            .map(|(table, path)| Ok((*table, snapshot(path)?)))
+            .collect::<Result<BTreeMap<_, _>, Error>>()?;
+        let fstab = tables
+            .into_iter()
+            .find(|(table, _)| *table == MountTable::Fstab)
+            .map(|(_, path)| FileWatch::new(&path))
+            .transpose()?;
+        let mut pending = VecDeque::new();
+        if fire_initial {
+            for (table, snapshot) in &snapshots {
+                pending.extend(mounts(*table, snapshot)?);
+            }
+        }
+        Ok(MonitorStream {
+            fd: AsyncFd::with_interest(MonitorFd { monitor: self, fd }, Interest::READABLE)?,
+            fstab,
+            tables: snapshots,
+            pending,
+        })
+    }
+    /// Calls `handler` with every change until it returns false or `condition` holds. While no
+    /// mounts change, `condition` is checked every `rate`, or not at all if `rate` is zero.
     pub async fn poll_until<'a, F>(
         self,
         rate: Duration,
-        mut current_table: Option<Table>,
+        tables: impl IntoIterator<Item = (MountTable, PathBuf)>,
         fire_initial: bool,
         mut condition: impl FnMut() -> bool,
         handler: F,
@@ -92,84 +135,26 @@ impl Monitor {
     where
         F: Fn(Event<'a>) -> bool,
     {
-        let millis = rate.as_millis() as std::ffi::c_int;
-        unsafe {
-            let output_ptr =
-                std::alloc::alloc_zeroed(Layout::new::<*mut *const c_char>()) as *mut *const c_char;
-            let mut ty = 0;
-            let mut result;
-            if fire_initial {
-                if let Some(table) = &current_table {
-                    for fs in table.iter()? {
-                        if !handler(Event::MountEvent(MountEvent::Mount { filesystem: fs? })) {
-                            return Ok(());
-                        }
-                    }
+        let mut stream = self.into_stream(tables, fire_initial)?;
+        while !condition() {
+            let event = if rate.is_zero() {
+                stream.next().await
+            } else {
+                match tokio::time::timeout(rate, stream.next()).await {
+                    Ok(event) => event,
+                    Err(_) => continue,
                 }
-            }
-
-            while !condition() {
-                result = mnt_monitor_wait(self.0, millis);
-                match result {
-                    0 => {
-                        continue;
-                    }
-                    1 => {
-                        result = mnt_monitor_next_change(self.0, output_ptr, &mut ty);
-                        match result {
-                            0 => {
-                                let value = CStr::from_ptr(*output_ptr);
-                                let path = Path::new(OsStr::from_bytes(value.to_bytes()));
-                                if !handler(Event::MountEvent(MountEvent::MonitorUpdate {
-                                    location: path,
-                                    monitor_type: ty.try_into()?,
-                                })) {
-                                    return Ok(());
-                                }
-
-                                let new_table = Table::read(&path)?;
-                                if let Some(table) = &current_table {
-                                    let iter = table.diff(&new_table)?;
-                                    for evt in iter {
-                                        if !handler(Event::MountEvent(evt?)) {
-                                            return Ok(());
-                                        }
-                                    }
-                                } else {
-                                    let iter = new_table.iter()?;
-                                    for fs in iter {
-                                        if !handler(Event::MountEvent(MountEvent::Mount {
-                                            filesystem: fs?,
-                                        })) {
-                                            return Ok(());
-                                        }
-                                    }
-                                }
-                                current_table.replace(new_table);
-                            }
-                            1 => {
-                                continue;
-                            }
-
-                            error => {
-                                return Err(Error::MonitorNextChange(error));
-                            }
-                        }
+            };
+            match event {
+                Some(event) => {
+                    if !handler(Event::MountEvent(event?)) {
+                        return Ok(());
                     }
-                    error => return Err(Error::MonitorPoll(error)),
                 }
+                None => return Ok(()),
             }
-            std::alloc::dealloc(output_ptr as *mut u8, Layout::new::<*mut *const c_char>());
-            Ok(())
-        }
-    }
-}
-impl Clone for Monitor {
-    fn clone(&self) -> Self {
-        unsafe {
-            mnt_ref_monitor(self.0);
-            Monitor(self.0)
         }
+        Ok(())
     }
 }
 impl Drop for Monitor {
@@ -179,3 +164,206 @@ impl Drop for Monitor {
         }
     }
 }
+struct MonitorFd {
+    monitor: Monitor,
+    fd: RawFd,
+}
+impl AsRawFd for MonitorFd {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd
+    }
+}
+/// A table file watched through its directory, as editors replace files rather than write them.
+struct FileWatch {
+    fd: AsyncFd<OwnedFd>,
+    path: PathBuf,
+    name: OsString,
+}
+impl FileWatch {
+    const MASK: u32 = libc::IN_CLOSE_WRITE
+        | libc::IN_CREATE
+        | libc::IN_DELETE
+        | libc::IN_MOVED_FROM
+        | libc::IN_MOVED_TO;
+    fn new(path: &Path) -> Result<Self, Error> {
+        let (Some(dir), Some(name)) = (path.parent(), path.file_name()) else {
+            return Err(std::io::Error::from(ErrorKind::InvalidInput).into());
+        };
+        let dir = CString::new(dir.as_os_str().as_bytes())?;
+        let fd = unsafe { libc::inotify_init1(libc::IN_NONBLOCK | libc::IN_CLOEXEC) };
+        if fd < 0 {
+            return Err(std::io::Error::last_os_error().into());
+        }
+        let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+        if unsafe { libc::inotify_add_watch(fd.as_raw_fd(), dir.as_ptr(), Self::MASK) } < 0 {
+            return Err(std::io::Error::last_os_error().into());
+        }
+        Ok(Self {
+            fd: AsyncFd::with_interest(fd, Interest::READABLE)?,
+            path: path.to_path_buf(),
+            name: name.to_os_string(),
+        })
+    }
+    /// Drains the queued notifications, returning whether any was about the file.
+    fn changed(fd: &OwnedFd, name: &OsStr) -> std::io::Result<bool> {
+        const HEADER: usize = size_of::<libc::inotify_event>();
+        let mut buffer = [0u8; 4096];
+        let mut changed = false;
+        loop {
+            let read =
+                unsafe { libc::read(fd.as_raw_fd(), buffer.as_mut_ptr().cast(), buffer.len()) };
+            if read < 0 {
+                let error = std::io::Error::last_os_error();
+                if error.kind() == ErrorKind::WouldBlock {
+                    return Ok(changed);
+                }
+                return Err(error);
+            }
+            let mut offset = 0;
+            while offset + HEADER <= read as usize {
+                let event = unsafe {
+                    std::ptr::read_unaligned(buffer[offset..].as_ptr() as *const libc::inotify_event)
+                };
+                let end = (offset + HEADER + event.len as usize).min(read as usize);
+                let event_name = buffer[offset + HEADER..end]
+                    .split(|byte| *byte == 0)
+                    .next()
+                    .unwrap_or_default();
+                changed |= event.mask & libc::IN_Q_OVERFLOW != 0 || event_name == name.as_bytes();
+                offset = end;
+            }
+        }
+    }
+}
+/// Mount changes of a [`Monitor`], woken by readiness of its file descriptor, and of fstab when
+/// it is watched.
+pub struct MonitorStream {
+    fd: AsyncFd<MonitorFd>,
+    fstab: Option<FileWatch>,
+    tables: BTreeMap<MountTable, Table>,
+    pending: VecDeque<Result<MountEvent<'static>, Error>>,
+}
+// The monitor and the tables are created for the stream and never shared, and events only carry
+// owned copies of their filesystems, so no libmount reference count is touched from two threads.
+unsafe impl Send for MonitorStream {}
+impl MonitorStream {
+    /// Queues the events of the next change, returning false once there are none left.
+    fn next_change(
+        monitor: &Monitor,
+        tables: &mut BTreeMap<MountTable, Table>,
+        pending: &mut VecDeque<Result<MountEvent<'static>, Error>>,
+    ) -> Result<bool, Error> {
+        let mut filename: *const c_char = null();
+        let mut ty = 0;
+        let path = unsafe {
+            match mnt_monitor_next_change(monitor.0, &mut filename, &mut ty) {
+                0 => {
+                    Path::new(OsStr::from_bytes(CStr::from_ptr(filename).to_bytes())).to_path_buf()
+                }
+                1 => return Ok(false),
+                error => return Err(Error::MonitorNextChange(error)),
+            }
+        };
+        let monitor_type = MonitorType::try_from(ty)?;
+        let snapshot = Table::read(&path)?;
+        pending.push_back(Ok(MountEvent::MonitorUpdate {
+            location: path.into(),
+            monitor_type,
+        }));
+        Self::update(MountTable::from(monitor_type), snapshot, tables, pending)?;
+        Ok(true)
+    }
+    /// Queues the differences between a table's snapshot and its new contents.
+    fn update(
+        table: MountTable,
+        snapshot: Table,
+        tables: &mut BTreeMap<MountTable, Table>,
+        pending: &mut VecDeque<Result<MountEvent<'static>, Error>>,
+    ) -> Result<(), Error> {
+        match tables.get(&table) {
+            Some(previous) => pending.extend(previous.diff(&snapshot, table)?),
+            None => pending.extend(mounts(table, &snapshot)?),
+        }
+        tables.insert(table, snapshot);
+        Ok(())
+    }
+}
+/// Reads a table, empty if its file doesn't exist, as fstab may not while it is replaced.
+fn snapshot(path: &Path) -> Result<Table, Error> {
+    match path.exists() {
+        true => Table::read(path),
+        false => Ok(Table::new().map_err(Error::AllocationTable)?),
+    }
+}
+/// Every filesystem of a table, as mounted.
+fn mounts(
+    table: MountTable,
+    snapshot: &Table,
+) -> Result<impl Iterator<Item = Result<MountEvent<'static>, Error>>, Error> {
+    Ok(snapshot.iter()?.map(move |fs| {
+        fs.map(|filesystem| MountEvent::Mount {
+            filesystem: Box::new(filesystem.info()),
+            table,
+        })
+    }))
+}
+impl Stream for MonitorStream {
+    type Item = Result<MountEvent<'static>, Error>;
+
+    fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        loop {
+            if let Some(event) = this.pending.pop_front() {
+                return Poll::Ready(Some(event));
+            }
+            let mut ready = false;
+            if let Poll::Ready(guard) = this.fd.poll_read_ready(cx) {
+                ready = true;
+                let mut guard = match guard {
+                    Ok(guard) => guard,
+                    Err(err) => return Poll::Ready(Some(Err(err.into()))),
+                };
+                match Self::next_change(
+                    &guard.get_inner().monitor,
+                    &mut this.tables,
+                    &mut this.pending,
+                ) {
+                    Ok(true) => {}
+                    Ok(false) => guard.clear_ready(),
+                    Err(err) => this.pending.push_back(Err(err)),
+                }
+            }
+            if let Some(fstab) = &this.fstab
+                && let Poll::Ready(guard) = fstab.fd.poll_read_ready(cx)
+            {
+                ready = true;
+                let mut guard = match guard {
+                    Ok(guard) => guard,
+                    Err(err) => return Poll::Ready(Some(Err(err.into()))),
+                };
+                let changed = FileWatch::changed(guard.get_inner(), &fstab.name);
+                guard.clear_ready();
+                let result = changed
+                    .map_err(Error::from)
+                    .and_then(|changed| match changed {
+                        true => Self::update(
+                            MountTable::Fstab,
+                            snapshot(&fstab.path)?,
+                            &mut this.tables,
+                            &mut this.pending,
+                        ),
+                        false => Ok(()),
+                    });
+                if let Err(err) = result {
+                    this.pending.push_back(Err(err));
+                }
+            }
+            if !ready {
+                return Poll::Pending;
+            }
+        }
+    }
+}
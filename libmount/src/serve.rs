@@ -1,16 +1,14 @@
-use std::{
-    borrow::Cow, collections::BTreeMap, marker::PhantomData, path::Path, sync::Arc, time::Duration,
-};
+use std::{borrow::Cow, marker::PhantomData, path::Path, sync::Arc, time::Duration};
 
 use tokio::sync::mpsc::UnboundedSender;
+use tokio_stream::StreamExt;
 use tokio_util::sync::CancellationToken;
 
 use crate::{
     error::{Error, ServeError},
-    event::{Event, MountEvent, MountEventMask},
-    monitor::Monitor,
-    table::Table,
-    util::{get_fstab_path, get_mtab_path, get_utab_path},
+    event::{Event, MountEvent, MountEventMask, MountTable},
+    monitor::{Monitor, MonitorStream},
+    util::{get_fstab_path, get_mountinfo_path, get_utab_path},
 };
 pub trait Handler<'a> {
     type Error: std::error::Error;
@@ -55,7 +53,7 @@ impl MonitorServe {
 pub struct MonitorServeBuilderNoHandler<'a> {
     userspace: Option<(bool, Option<&'a Path>)>,
     kernel: Option<bool>,
-    poll_rate: Option<Duration>,
+    fstab: Option<(bool, Option<&'a Path>)>,
 }
 impl<'a> MonitorServeBuilderNoHandler<'a> {
     pub fn with_handler<E>(
@@ -72,7 +70,7 @@ impl<'a> MonitorServeBuilderNoHandler<'a> {
             handlers,
             userspace: self.userspace,
             kernel: self.kernel,
-            poll_rate: self.poll_rate,
+            fstab: self.fstab,
             err: PhantomData::default(),
         }
     }
@@ -84,8 +82,13 @@ impl<'a> MonitorServeBuilderNoHandler<'a> {
         self.kernel = Some(value);
         self
     }
-    pub fn with_poll_rate(mut self, value: Duration) -> Self {
-        self.poll_rate = Some(value);
+    /// Watches fstab, `file` or the default one, reporting edits as [`MountTable::Fstab`] events.
+    pub fn with_fstab(mut self, value: bool, file: Option<&'a Path>) -> Self {
+        self.fstab = Some((value, file));
+        self
+    }
+    #[deprecated(note = "changes are read as the monitor reports them, there is nothing to poll")]
+    pub fn with_poll_rate(self, _: Duration) -> Self {
         self
     }
 }
@@ -97,7 +100,7 @@ pub struct MonitorServeBuilder<'a, E> {
     )>,
     userspace: Option<(bool, Option<&'a Path>)>,
     kernel: Option<bool>,
-    poll_rate: Option<Duration>,
+    fstab: Option<(bool, Option<&'a Path>)>,
     err: PhantomData<E>,
 }
 impl<'a, E> Default for MonitorServeBuilder<'a, E> {
@@ -106,7 +109,7 @@ impl<'a, E> Default for MonitorServeBuilder<'a, E> {
             handlers: Vec::default(),
             userspace: Option::default(),
             kernel: Option::default(),
-            poll_rate: Option::default(),
+            fstab: Option::default(),
             err: PhantomData::default(),
         }
     }
@@ -133,8 +136,13 @@ where
         self.kernel = Some(value);
         self
     }
-    pub fn with_poll_rate(mut self, value: Duration) -> Self {
-        self.poll_rate = Some(value);
+    /// Watches fstab, `file` or the default one, reporting edits as [`MountTable::Fstab`] events.
+    pub fn with_fstab(mut self, value: bool, file: Option<&'a Path>) -> Self {
+        self.fstab = Some((value, file));
+        self
+    }
+    #[deprecated(note = "changes are read as the monitor reports them, there is nothing to poll")]
+    pub fn with_poll_rate(self, _: Duration) -> Self {
         self
     }
 }
@@ -201,11 +209,8 @@ where
             )
         }
         fn create_poll_handler<E>(
-            monitor: Monitor,
+            mut events: MonitorStream,
             event_sender: tokio::sync::mpsc::UnboundedSender<Event<'static>>,
-            poll_rate: Duration,
-            fstab: bool,
-            mtab: bool,
         ) -> (
             impl Future<Output = Result<(), ServeError<E>>> + 'static,
             tokio::sync::oneshot::Sender<()>,
@@ -217,35 +222,19 @@ where
             (
                 async move {
                     let mut rx = rx;
-                    let event_sender_poll = event_sender.clone();
-                    let mut initial = BTreeMap::<Cow<'_, Path>, Table>::new();
-                    if fstab {
-                        initial.insert(
-                            get_fstab_path(),
-                            Table::parse_fstab(None).map_err(Error::AllocationTable)?,
-                        );
-                    }
-                    if mtab {
-                        initial.insert(
-                            get_mtab_path(),
-                            Table::parse_mtab(None).map_err(Error::AllocationTable)?,
-                        );
-                    }
-                    monitor
-                        .poll_until(
-                            poll_rate,
-                            initial,
-                            true,
-                            move || match rx.try_recv() {
-                                Ok(_) => true,
-                                Err(err) => match err {
-                                    tokio::sync::oneshot::error::TryRecvError::Empty => false,
-                                    tokio::sync::oneshot::error::TryRecvError::Closed => true,
-                                },
+                    loop {
+                        let evt = tokio::select! {
+                            _ = &mut rx => break,
+                            evt = events.next() => match evt {
+                                Some(Ok(evt)) => Event::MountEvent(evt),
+                                Some(Err(err)) => Event::Error(err),
+                                None => break,
                             },
-                            |evt| event_sender_poll.send(evt).is_ok(),
-                        )
-                        .await?;
+                        };
+                        if event_sender.send(evt).is_err() {
+                            break;
+                        }
+                    }
                     let _ = event_sender.send(Event::Close);
                     Ok(())
                 },
@@ -259,14 +248,21 @@ where
         if let Some((userspace, file)) = self.userspace {
             monitor.with_userspace(userspace, file)?;
         }
+        let mut tables = Vec::new();
+        if self.kernel == Some(true) {
+            tables.push((MountTable::Kernel, get_mountinfo_path().into_owned()));
+        }
+        if let Some((true, file)) = self.userspace {
+            let path = file.map_or_else(get_utab_path, Cow::Borrowed);
+            tables.push((MountTable::Userspace, path.into_owned()));
+        }
+        if let Some((true, file)) = self.fstab {
+            let path = file.map_or_else(get_fstab_path, Cow::Borrowed);
+            tables.push((MountTable::Fstab, path.into_owned()));
+        }
+        let events = monitor.into_stream(tables, true)?;
         let (event_future, event_sender, error_receiver) = create_event_handler(self.handlers);
-        let (poll_future, close_signal) = create_poll_handler(
-            monitor,
-            event_sender,
-            self.poll_rate.unwrap_or(Duration::ZERO),
-            true,
-            true,
-        );
+        let (poll_future, close_signal) = create_poll_handler(events, event_sender);
         Ok((
             async move {
                 let event_handle = tokio::spawn(event_future);
@@ -1,10 +1,11 @@
 use std::{
-    alloc::Layout, ffi::c_char, marker::PhantomData, os::unix::ffi::OsStrExt, path::Path, ptr::null,
+    alloc::Layout, ffi::CString, marker::PhantomData, os::unix::ffi::OsStrExt, path::Path,
+    ptr::null,
 };
 
 use crate::{
     error::{AllocationError, Error},
-    event::MountEvent,
+    event::{MountEvent, MountTable},
     fs::FileSystem,
     iter::IterInternal,
     libmount::root::{
@@ -29,17 +30,14 @@ impl Table {
             }
         }
     }
-    pub fn read(path: impl AsRef<Path>) -> Result<Self, AllocationError<Self>> {
+    pub fn read(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let path = CString::new(path.as_ref().as_os_str().as_bytes())?;
         unsafe {
-            let path = path.as_ref();
-            println!("Path: {path:?}");
-
-            let path = path.as_os_str().as_bytes().as_ptr() as *const c_char;
-            let result = mnt_new_table_from_file(path);
+            let result = mnt_new_table_from_file(path.as_ptr());
             if !result.is_null() {
                 Ok(Self(result))
             } else {
-                Err(AllocationError::default())
+                Err(AllocationError::<Self>::default().into())
             }
         }
     }
@@ -67,9 +65,10 @@ impl Table {
             }
         }
     }
-    pub fn diff<'a>(&self, other: &Table) -> Result<Iter<'a, TableDiff>, Error> {
+    /// Changes from this snapshot of `table` to `other`.
+    pub fn diff<'a>(&self, other: &Table, table: MountTable) -> Result<Iter<'a, TableDiff>, Error> {
         unsafe {
-            let df = TableDiff::new()?;
+            let df = TableDiff::new(table)?;
             mnt_diff_tables(df.0, self.0, other.0);
             let iter = IterInternal::new(crate::iter::Direction::Forward)?;
             Ok(Iter(df, iter, PhantomData::default()))
@@ -105,7 +104,7 @@ impl<'a> Iterator for Iter<'a, Table> {
                 as *mut *mut libmnt_fs;
             let result = mnt_table_next_fs(self.0.0, self.1.0, fs);
             match result {
-                0 => Some(Ok(FileSystem(*fs))),
+                0 => Some(Ok(FileSystem::borrowed(*fs))),
                 1 => None,
                 err => Some(Err(Error::Iter(err))),
             }
@@ -131,23 +130,29 @@ impl<'a> Iterator for Iter<'a, TableDiff> {
                             return Some(Err(err));
                         }
                     };
+                    let table = self.0.1;
                     match operation {
                         DiffOperation::Move => Some(Ok(MountEvent::Move {
-                            from: FileSystem(*old),
-                            to: FileSystem(*new),
+                            from: Box::new(FileSystem::borrowed(*old).info()),
+                            to: Box::new(FileSystem::borrowed(*new).info()),
+                            table,
                         })),
                         DiffOperation::UMount => Some(Ok(MountEvent::UMount {
-                            filesystem: FileSystem(*old),
+                            filesystem: Box::new(FileSystem::borrowed(*old).info()),
+                            table,
                         })),
                         DiffOperation::Remount => Some(Ok(MountEvent::Remount {
-                            filesystem: FileSystem(*new),
+                            filesystem: Box::new(FileSystem::borrowed(*new).info()),
+                            table,
                         })),
                         DiffOperation::Mount => Some(Ok(MountEvent::Mount {
-                            filesystem: FileSystem(*new),
+                            filesystem: Box::new(FileSystem::borrowed(*new).info()),
+                            table,
                         })),
                         DiffOperation::Propagation => Some(Ok(MountEvent::Propagate {
-                            parent: FileSystem(*old),
-                            child: FileSystem(*new),
+                            parent: Box::new(FileSystem::borrowed(*old).info()),
+                            child: Box::new(FileSystem::borrowed(*new).info()),
+                            table,
                         })),
                     }
                 }
@@ -180,13 +185,13 @@ impl TryFrom<i32> for DiffOperation {
     }
 }
 
-pub struct TableDiff(*mut libmnt_tabdiff);
+pub struct TableDiff(*mut libmnt_tabdiff, MountTable);
 impl TableDiff {
-    fn new() -> Result<Self, AllocationError<Self>> {
+    fn new(table: MountTable) -> Result<Self, AllocationError<Self>> {
         unsafe {
             let value = mnt_new_tabdiff();
             if !value.is_null() {
-                Ok(Self(value))
+                Ok(Self(value, table))
             } else {
                 Err(AllocationError::default())
             }
@@ -30,3 +30,6 @@ pub fn get_fstab_path<'a>() -> Cow<'a, Path> {
         },
     }
 }
+pub fn get_mountinfo_path<'a>() -> Cow<'a, Path> {
+    Path::new("/proc/self/mountinfo").into()
+}
@@ -1,6 +1,14 @@
 mod manifest;
+#[cfg(feature = "passphrase")]
+pub mod passphrase;
 #[cfg(feature = "resolver")]
 mod resolver;
+#[cfg(feature = "signing")]
+pub mod signature;
+#[cfg(feature = "template")]
+pub mod template;
+mod validate;
 pub use manifest::*;
 #[cfg(feature = "resolver")]
 pub use resolver::*;
+pub use validate::*;
@@ -1,7 +1,14 @@
-use std::path::{Path, PathBuf};
+use std::{
+    fmt::Display,
+    path::{Path, PathBuf},
+};
 
 use builder::Builder;
-use serde::{Deserialize, Serialize, Serializer};
+use http::Uri;
+use serde::{
+    Deserialize, Serialize, Serializer,
+    de::{MapAccess, Visitor, value::MapAccessDeserializer},
+};
 use toml::{Value, map::Map};
 #[derive(Serialize, Deserialize, Clone, Debug, Builder)]
 pub struct Manifest {
@@ -12,44 +19,194 @@ pub struct Manifest {
     properties: Map<String, Value>,
     #[builder(setter_name = "label")]
     labels: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[builder(setter_name = "template")]
+    templates: Vec<Template>,
 }
 impl Manifest {
     pub const NAME: &'static str = "Hairpin.toml";
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    pub fn version(&self) -> &str {
+        &self.version
+    }
+    pub fn items(&self) -> &[Item] {
+        &self.items
+    }
+    pub fn properties(&self) -> &Map<String, Value> {
+        &self.properties
+    }
+    pub fn labels(&self) -> &[String] {
+        &self.labels
+    }
+    pub fn templates(&self) -> &[Template] {
+        &self.templates
+    }
+    pub fn add_item(&mut self, item: Item) {
+        self.items.push(item);
+    }
+    /// Removes the item with the given id or, failing that, the given name.
+    pub fn remove_item(&mut self, key: &str) -> Option<Item> {
+        let index = self
+            .items
+            .iter()
+            .position(|item| item.id == key)
+            .or_else(|| self.items.iter().position(|item| item.name == key))?;
+        Some(self.items.remove(index))
+    }
 }
-#[derive(Serialize, Deserialize, Clone, Debug, Builder)]
+/// A file of the source rendered from item values and delivered next to them.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct Template {
+    /// Template file, relative to the source root.
+    source: PathBuf,
+    /// File name the rendered template is delivered as.
+    target: String,
+    /// Hex encoded digest of the template file.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    sha256: Option<String>,
+}
+impl Template {
+    pub fn new(source: impl Into<PathBuf>, target: impl Into<String>) -> Self {
+        Self {
+            source: source.into(),
+            target: target.into(),
+            sha256: None,
+        }
+    }
+    pub fn with_sha256(mut self, sha256: impl Into<String>) -> Self {
+        self.sha256 = Some(sha256.into());
+        self
+    }
+    pub fn source(&self) -> &Path {
+        &self.source
+    }
+    pub fn target(&self) -> &str {
+        &self.target
+    }
+    pub fn sha256(&self) -> Option<&str> {
+        self.sha256.as_deref()
+    }
+}
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Builder)]
 pub struct Item {
     id: String,
     name: String,
+    #[serde(default, skip_serializing_if = "ValueAccessor::is_none")]
     value: ValueAccessor,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    sha256: Option<String>,
     encryption: ItemEncryption,
     properties: Map<String, Value>,
+    #[builder(setter_name = "label")]
     labels: Vec<String>,
 }
-#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+impl Item {
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    pub fn value(&self) -> &ValueAccessor {
+        &self.value
+    }
+    /// Hex encoded digest of the stored value, before decryption.
+    pub fn sha256(&self) -> Option<&str> {
+        self.sha256.as_deref()
+    }
+    pub fn encryption(&self) -> &ItemEncryption {
+        &self.encryption
+    }
+    pub fn properties(&self) -> &Map<String, Value> {
+        &self.properties
+    }
+    pub fn labels(&self) -> &[String] {
+        &self.labels
+    }
+}
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, Hash)]
 #[serde(rename_all = "kebab-case")]
 pub enum ItemEncryption {
     #[default]
     None,
     PlainText,
+    Age,
+    Passphrase(KdfParameters),
+}
+impl Display for ItemEncryption {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ItemEncryption::None => f.write_str("none"),
+            ItemEncryption::PlainText => f.write_str("plain-text"),
+            ItemEncryption::Age => f.write_str("age"),
+            ItemEncryption::Passphrase(_) => f.write_str("passphrase"),
+        }
+    }
+}
+/// scrypt parameters and hex encoded salt used to derive a passphrase key.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+#[serde(rename_all = "kebab-case")]
+pub struct KdfParameters {
+    salt: String,
+    log_n: u8,
+    r: u32,
+    p: u32,
 }
-#[derive(Clone, Debug, Default)]
+impl KdfParameters {
+    /// Highest scrypt cost accepted from manifests, 1 GiB of memory at most.
+    pub const MAX_LOG_N: u8 = 20;
+    pub const MAX_R: u32 = 8;
+    pub const MAX_P: u32 = 4;
+    pub fn new(salt: String, log_n: u8, r: u32, p: u32) -> Self {
+        Self { salt, log_n, r, p }
+    }
+    /// Whether deriving a key stays within the accepted cost.
+    pub fn is_bounded(&self) -> bool {
+        self.log_n <= Self::MAX_LOG_N && self.r <= Self::MAX_R && self.p <= Self::MAX_P
+    }
+    pub fn salt(&self) -> &str {
+        &self.salt
+    }
+    pub fn log_n(&self) -> u8 {
+        self.log_n
+    }
+    pub fn r(&self) -> u32 {
+        self.r
+    }
+    pub fn p(&self) -> u32 {
+        self.p
+    }
+}
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
 pub enum ValueAccessor {
     #[default]
     None,
     Path(PathBuf),
-    Remote,
+    Remote(RemoteValue),
+}
+impl ValueAccessor {
+    pub fn is_none(&self) -> bool {
+        matches!(self, ValueAccessor::None)
+    }
 }
 impl Serialize for ValueAccessor {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: Serializer,
     {
-        if let ValueAccessor::Path(value) = self {
-            if let Some(value) = value.to_str() {
-                return serializer.serialize_str(value);
-            }
+        match self {
+            ValueAccessor::None => serializer.serialize_none(),
+            ValueAccessor::Path(value) => match value.to_str() {
+                Some(value) => serializer.serialize_str(value),
+                None => Err(serde::ser::Error::custom("value path is not valid UTF-8")),
+            },
+            ValueAccessor::Remote(value) => value.serialize(serializer),
         }
-        serializer.serialize_unit()
     }
 }
 impl<'de> Deserialize<'de> for ValueAccessor {
@@ -57,7 +214,78 @@ impl<'de> Deserialize<'de> for ValueAccessor {
     where
         D: serde::Deserializer<'de>,
     {
-        String::deserialize(deserializer)
-            .map(|value| ValueAccessor::Path(Path::new(value.as_str()).to_path_buf()))
+        struct AccessorVisitor;
+        impl<'de> Visitor<'de> for AccessorVisitor {
+            type Value = ValueAccessor;
+
+            fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                formatter.write_str("a path relative to the source or a table with a url")
+            }
+            fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
+            where
+                E: serde::de::Error,
+            {
+                Ok(ValueAccessor::Path(Path::new(value).to_path_buf()))
+            }
+            fn visit_map<A>(self, map: A) -> Result<Self::Value, A::Error>
+            where
+                A: MapAccess<'de>,
+            {
+                RemoteValue::deserialize(MapAccessDeserializer::new(map)).map(ValueAccessor::Remote)
+            }
+            fn visit_none<E>(self) -> Result<Self::Value, E>
+            where
+                E: serde::de::Error,
+            {
+                Ok(ValueAccessor::None)
+            }
+            fn visit_unit<E>(self) -> Result<Self::Value, E>
+            where
+                E: serde::de::Error,
+            {
+                Ok(ValueAccessor::None)
+            }
+        }
+        deserializer.deserialize_any(AccessorVisitor)
+    }
+}
+/// An item value fetched from a url, optionally pinned to a SHA-256 digest.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct RemoteValue {
+    #[serde(with = "uri")]
+    url: Uri,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    sha256: Option<String>,
+}
+impl RemoteValue {
+    pub fn new(url: Uri, sha256: Option<String>) -> Self {
+        Self { url, sha256 }
+    }
+    pub fn url(&self) -> &Uri {
+        &self.url
+    }
+    /// Hex encoded digest the fetched value must match.
+    pub fn sha256(&self) -> Option<&str> {
+        self.sha256.as_deref()
+    }
+}
+mod uri {
+    use std::str::FromStr;
+
+    use http::Uri;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S>(value: &Uri, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_str(value)
+    }
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Uri, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let value = String::deserialize(deserializer)?;
+        Uri::from_str(&value).map_err(serde::de::Error::custom)
     }
 }
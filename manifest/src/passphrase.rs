@@ -0,0 +1,102 @@
+use chacha20poly1305::{
+    ChaCha20Poly1305, KeyInit, Nonce,
+    aead::{Aead, AeadCore, OsRng, rand_core::RngCore},
+};
+use zeroize::Zeroizing;
+
+use crate::KdfParameters;
+
+pub const KEY_LEN: usize = 32;
+pub const NONCE_LEN: usize = 12;
+pub const SALT_LEN: usize = 16;
+pub type Key = Zeroizing<[u8; KEY_LEN]>;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("Invalid scrypt parameters: {0}")]
+    InvalidParameters(#[from] scrypt::errors::InvalidParams),
+    #[error(
+        "scrypt parameters log-n {0}, r {1}, p {2} exceed the accepted cost of log-n {max_log_n}, r {max_r}, p {max_p}",
+        max_log_n = KdfParameters::MAX_LOG_N,
+        max_r = KdfParameters::MAX_R,
+        max_p = KdfParameters::MAX_P
+    )]
+    TooCostly(u8, u32, u32),
+    #[error("Invalid salt: {0}")]
+    InvalidSalt(#[from] hex::FromHexError),
+    #[error("Ciphertext is truncated")]
+    Truncated,
+    #[error("Incorrect passphrase or corrupted value")]
+    Decryption,
+    #[error("Unable to encrypt value")]
+    Encryption,
+}
+impl KdfParameters {
+    pub const DEFAULT_LOG_N: u8 = 15;
+    pub const DEFAULT_R: u32 = 8;
+    pub const DEFAULT_P: u32 = 1;
+    /// Default scrypt cost with a fresh random salt.
+    pub fn generate() -> Self {
+        let mut salt = [0u8; SALT_LEN];
+        OsRng.fill_bytes(&mut salt);
+        Self::new(
+            hex::encode(salt),
+            Self::DEFAULT_LOG_N,
+            Self::DEFAULT_R,
+            Self::DEFAULT_P,
+        )
+    }
+    pub fn derive(&self, passphrase: &[u8]) -> Result<Key, Error> {
+        if !self.is_bounded() {
+            return Err(Error::TooCostly(self.log_n(), self.r(), self.p()));
+        }
+        let params = scrypt::Params::new(self.log_n(), self.r(), self.p(), KEY_LEN)?;
+        let salt = hex::decode(self.salt())?;
+        let mut key = Zeroizing::new([0u8; KEY_LEN]);
+        scrypt::scrypt(passphrase, &salt, &params, key.as_mut_slice())
+            .map_err(|_| Error::InvalidParameters(scrypt::errors::InvalidParams))?;
+        Ok(key)
+    }
+}
+/// Encrypts a value with ChaCha20-Poly1305, prefixing the random nonce.
+pub fn seal(key: &[u8; KEY_LEN], value: &[u8]) -> Result<Vec<u8>, Error> {
+    let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let ciphertext = ChaCha20Poly1305::new(key.into())
+        .encrypt(&nonce, value)
+        .map_err(|_| Error::Encryption)?;
+    let mut output = Vec::with_capacity(NONCE_LEN + ciphertext.len());
+    output.extend_from_slice(nonce.as_slice());
+    output.extend(ciphertext);
+    Ok(output)
+}
+pub fn open(key: &[u8; KEY_LEN], value: &[u8]) -> Result<Vec<u8>, Error> {
+    if value.len() < NONCE_LEN {
+        return Err(Error::Truncated);
+    }
+    let (nonce, ciphertext) = value.split_at(NONCE_LEN);
+    ChaCha20Poly1305::new(key.into())
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| Error::Decryption)
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn costly_parameters_are_refused_before_deriving() {
+        let parameters = KdfParameters::new("00".repeat(SALT_LEN), 30, 8, 1);
+        assert!(matches!(
+            parameters.derive(b"passphrase"),
+            Err(Error::TooCostly(30, 8, 1))
+        ));
+    }
+    #[test]
+    fn sealed_values_open_with_the_same_key() {
+        let parameters = KdfParameters::new("00".repeat(SALT_LEN), 4, 8, 1);
+        let key = parameters.derive(b"passphrase").unwrap();
+        let sealed = seal(&key, b"value").unwrap();
+        assert_eq!(open(&key, &sealed).unwrap(), b"value");
+        let other = parameters.derive(b"other").unwrap();
+        assert!(open(&other, &sealed).is_err());
+    }
+}
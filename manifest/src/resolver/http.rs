@@ -0,0 +1,261 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::Duration,
+};
+
+use reqwest::{
+    Certificate, Client, Response, StatusCode, Url,
+    header::{ETAG, IF_NONE_MATCH},
+};
+
+use crate::{Diagnostic, Manifest};
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error(transparent)]
+    Request(#[from] reqwest::Error),
+    #[error("{0} responded with {1}")]
+    Status(Url, StatusCode),
+    #[error("{0} is larger than {1} bytes")]
+    TooLarge(Url, u64),
+    #[error("Invalid url {0}: {1}")]
+    InvalidUrl(String, String),
+    #[error("Item path {0} escapes the manifest location")]
+    Escapes(String),
+    #[error("Unable to read CA bundle {0}: {1}")]
+    CaBundle(PathBuf, String),
+    #[error(transparent)]
+    InvalidManifest(#[from] toml::de::Error),
+    #[error("Manifest failed validation: {}", .0.iter().map(ToString::to_string).collect::<Vec<_>>().join("; "))]
+    Validation(Vec<Diagnostic>),
+}
+#[derive(Debug, Clone)]
+pub struct HttpOptions {
+    timeout: Duration,
+    max_size: u64,
+    ca_bundle: Option<PathBuf>,
+}
+impl Default for HttpOptions {
+    fn default() -> Self {
+        Self {
+            timeout: Self::DEFAULT_TIMEOUT,
+            max_size: Self::DEFAULT_MAX_SIZE,
+            ca_bundle: None,
+        }
+    }
+}
+impl HttpOptions {
+    pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+    pub const DEFAULT_MAX_SIZE: u64 = 16 * 1024 * 1024;
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+    pub fn with_max_size(mut self, max_size: u64) -> Self {
+        self.max_size = max_size;
+        self
+    }
+    /// Trusts only the certificates in this PEM bundle instead of the built-in roots.
+    pub fn with_ca_bundle(mut self, ca_bundle: PathBuf) -> Self {
+        self.ca_bundle = Some(ca_bundle);
+        self
+    }
+}
+#[derive(Debug)]
+struct Cached {
+    etag: String,
+    body: Vec<u8>,
+}
+/// Fetches manifests and item values over HTTP(S), revalidating cached manifests by ETag. Item
+/// values are never cached, so secrets don't outlive the request that read them.
+#[derive(Debug)]
+pub struct HttpResolver {
+    client: Client,
+    max_size: u64,
+    cache: Mutex<HashMap<Url, Cached>>,
+}
+impl HttpResolver {
+    pub fn new(options: &HttpOptions) -> Result<Self, Error> {
+        let mut builder = Client::builder()
+            .timeout(options.timeout)
+            .connect_timeout(options.timeout)
+            .redirect(reqwest::redirect::Policy::none());
+        if let Some(path) = &options.ca_bundle {
+            let bundle = std::fs::read(path)
+                .map_err(|err| Error::CaBundle(path.clone(), err.to_string()))?;
+            builder = builder.tls_built_in_root_certs(false);
+            for certificate in Certificate::from_pem_bundle(&bundle)
+                .map_err(|err| Error::CaBundle(path.clone(), err.to_string()))?
+            {
+                builder = builder.add_root_certificate(certificate);
+            }
+        }
+        Ok(Self {
+            client: builder.build()?,
+            max_size: options.max_size,
+            cache: Mutex::new(HashMap::new()),
+        })
+    }
+    /// The manifest url, appending `Hairpin.toml` when the location names a directory.
+    pub fn manifest_url(location: &str) -> Result<Url, Error> {
+        let url = Url::parse(location)
+            .map_err(|err| Error::InvalidUrl(location.to_string(), err.to_string()))?;
+        if url.path().ends_with('/') {
+            url.join(Manifest::NAME)
+                .map_err(|err| Error::InvalidUrl(location.to_string(), err.to_string()))
+        } else {
+            Ok(url)
+        }
+    }
+    /// Resolves an item path against the manifest url, refusing anything outside its directory.
+    pub fn item_url(manifest: &Url, path: &Path) -> Result<Url, Error> {
+        let relative = path.to_string_lossy();
+        let escapes = path.is_absolute()
+            || path
+                .components()
+                .any(|component| component == std::path::Component::ParentDir);
+        let url = manifest
+            .join(&relative)
+            .map_err(|err| Error::InvalidUrl(relative.to_string(), err.to_string()))?;
+        let base = manifest
+            .join(".")
+            .map_err(|err| Error::InvalidUrl(manifest.to_string(), err.to_string()))?;
+        if escapes || url.origin() != base.origin() || !url.path().starts_with(base.path()) {
+            return Err(Error::Escapes(relative.to_string()));
+        }
+        Ok(url)
+    }
+    pub async fn manifest(&self, location: &str) -> Result<Manifest, Error> {
+        let body = self.fetch_cached(&Self::manifest_url(location)?).await?;
+        let document = String::from_utf8_lossy(&body);
+        let diagnostics = Manifest::validate(&document, None)
+            .into_iter()
+            .filter(Diagnostic::is_error)
+            .collect::<Vec<_>>();
+        if !diagnostics.is_empty() {
+            return Err(Error::Validation(diagnostics));
+        }
+        Ok(toml::from_str(&document)?)
+    }
+    /// The detached signature url, the manifest path with `.sig` appended and its query kept.
+    pub fn signature_url(location: &str) -> Result<Url, Error> {
+        let mut url = Self::manifest_url(location)?;
+        url.set_path(&format!("{}.sig", url.path()));
+        Ok(url)
+    }
+    /// Fetches the detached signature published next to the manifest, if the source is signed.
+    pub async fn signature(&self, location: &str) -> Result<Option<String>, Error> {
+        match self.fetch(&Self::signature_url(location)?).await {
+            Ok(body) => Ok(Some(String::from_utf8_lossy(&body).into_owned())),
+            Err(Error::Status(_, StatusCode::NOT_FOUND)) => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+    pub async fn get(&self, url: &str) -> Result<Vec<u8>, Error> {
+        let url =
+            Url::parse(url).map_err(|err| Error::InvalidUrl(url.to_string(), err.to_string()))?;
+        self.fetch(&url).await
+    }
+    pub async fn item(&self, location: &str, path: &Path) -> Result<Vec<u8>, Error> {
+        self.fetch(&Self::item_url(&Self::manifest_url(location)?, path)?)
+            .await
+    }
+    pub async fn fetch(&self, url: &Url) -> Result<Vec<u8>, Error> {
+        let response = self.client.get(url.clone()).send().await?;
+        Ok(self.body(url, response).await?.1)
+    }
+    /// Fetches a manifest, sending the ETag of the last response so unchanged ones aren't
+    /// downloaded again.
+    async fn fetch_cached(&self, url: &Url) -> Result<Vec<u8>, Error> {
+        let etag = self
+            .cache
+            .lock()
+            .ok()
+            .and_then(|cache| cache.get(url).map(|cached| cached.etag.clone()));
+        let mut request = self.client.get(url.clone());
+        if let Some(etag) = &etag {
+            request = request.header(IF_NONE_MATCH, etag);
+        }
+        let response = request.send().await?;
+        if response.status() == StatusCode::NOT_MODIFIED
+            && let Some(cached) = self
+                .cache
+                .lock()
+                .ok()
+                .and_then(|cache| cache.get(url).map(|cached| cached.body.clone()))
+        {
+            return Ok(cached);
+        }
+        let (etag, body) = self.body(url, response).await?;
+        if let (Some(etag), Ok(mut cache)) = (etag, self.cache.lock()) {
+            cache.insert(
+                url.clone(),
+                Cached {
+                    etag,
+                    body: body.clone(),
+                },
+            );
+        }
+        Ok(body)
+    }
+    /// Reads a successful response up to the size limit, along with its ETag.
+    async fn body(
+        &self,
+        url: &Url,
+        mut response: Response,
+    ) -> Result<(Option<String>, Vec<u8>), Error> {
+        if !response.status().is_success() {
+            return Err(Error::Status(url.clone(), response.status()));
+        }
+        if response
+            .content_length()
+            .is_some_and(|length| length > self.max_size)
+        {
+            return Err(Error::TooLarge(url.clone(), self.max_size));
+        }
+        let etag = response
+            .headers()
+            .get(ETAG)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+        let mut body = Vec::new();
+        while let Some(chunk) = response.chunk().await? {
+            if body.len() as u64 + chunk.len() as u64 > self.max_size {
+                return Err(Error::TooLarge(url.clone(), self.max_size));
+            }
+            body.extend_from_slice(&chunk);
+        }
+        Ok((etag, body))
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signature_is_next_to_the_manifest() {
+        let url = |location| HttpResolver::signature_url(location).unwrap().to_string();
+        assert_eq!(
+            url("https://example.com/app/"),
+            "https://example.com/app/Hairpin.toml.sig"
+        );
+        assert_eq!(
+            url("https://example.com/app/Hairpin.toml?ref=main"),
+            "https://example.com/app/Hairpin.toml.sig?ref=main"
+        );
+    }
+    #[test]
+    fn items_stay_under_the_manifest() {
+        let manifest = HttpResolver::manifest_url("https://example.com/app/").unwrap();
+        let item = |path| HttpResolver::item_url(&manifest, Path::new(path));
+        assert_eq!(
+            item("items/a").unwrap().as_str(),
+            "https://example.com/app/items/a"
+        );
+        for path in ["../a", "/a", "//other.example.com/a"] {
+            assert!(matches!(item(path), Err(Error::Escapes(_))), "{path}");
+        }
+    }
+}
@@ -1,3 +1,6 @@
+#[cfg(feature = "http")]
+pub mod http;
 pub mod path;
 mod resolver;
+pub mod value;
 pub use resolver::*;
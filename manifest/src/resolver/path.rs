@@ -1,6 +1,6 @@
 use std::path::{Path, PathBuf};
 
-use crate::Manifest;
+use crate::{Diagnostic, Manifest};
 
 use super::ManifestResolver;
 #[derive(Debug, thiserror::Error)]
@@ -9,15 +9,39 @@ pub enum Error {
     IO(#[from] std::io::Error),
     #[error(transparent)]
     InvalidManifest(#[from] toml::de::Error),
+    #[error("Manifest failed validation: {}", .0.iter().map(ToString::to_string).collect::<Vec<_>>().join("; "))]
+    Validation(Vec<Diagnostic>),
 }
-async fn resolve_path(value: impl AsRef<Path>) -> Result<Manifest, Error> {
-    let value = value.as_ref();
-    let manifest_file = if value.is_dir() {
+/// The manifest file of a source, which is either the file itself or a directory containing it.
+pub fn manifest_file(value: &Path) -> PathBuf {
+    if value.is_dir() {
         value.join(Manifest::NAME)
     } else {
         value.to_path_buf()
-    };
-    let manifest = toml::from_str(tokio::fs::read_to_string(manifest_file).await?.as_str())?;
+    }
+}
+/// Reads the detached signature next to the manifest file, if the source is signed.
+pub async fn signature(value: impl AsRef<Path>) -> Result<Option<String>, Error> {
+    let mut signature = manifest_file(value.as_ref()).into_os_string();
+    signature.push(".sig");
+    match tokio::fs::read_to_string(signature).await {
+        Ok(signature) => Ok(Some(signature)),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err.into()),
+    }
+}
+async fn resolve_path(value: impl AsRef<Path>) -> Result<Manifest, Error> {
+    let manifest_file = manifest_file(value.as_ref());
+    let document = tokio::fs::read_to_string(&manifest_file).await?;
+    let root = manifest_file.parent().unwrap_or(Path::new("."));
+    let diagnostics = Manifest::validate(&document, Some(root))
+        .into_iter()
+        .filter(Diagnostic::is_error)
+        .collect::<Vec<_>>();
+    if !diagnostics.is_empty() {
+        return Err(Error::Validation(diagnostics));
+    }
+    let manifest = toml::from_str(document.as_str())?;
     Ok(manifest)
 }
 impl ManifestResolver for PathBuf {
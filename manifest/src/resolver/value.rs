@@ -0,0 +1,289 @@
+use std::{
+    os::fd::AsRawFd,
+    path::{Component, Path, PathBuf},
+};
+
+use sha2::{Digest, Sha256};
+use tokio::io::AsyncReadExt;
+
+#[cfg(feature = "http")]
+use super::http::HttpResolver;
+use crate::{Item, RemoteValue, Template, ValueAccessor};
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error(transparent)]
+    IO(#[from] std::io::Error),
+    #[cfg(feature = "http")]
+    #[error(transparent)]
+    Http(#[from] super::http::Error),
+    #[error("Value path {0} escapes the source root")]
+    Escapes(PathBuf),
+    #[error("No http resolver is available to fetch {0}")]
+    RemoteUnavailable(String),
+    #[error("Remote value {0} is not allowed: {1}")]
+    RemoteDenied(String, &'static str),
+    #[error("Digest mismatch for {name}: expected {expected}, got {actual}")]
+    Digest {
+        name: String,
+        expected: String,
+        actual: String,
+    },
+}
+/// Where relative value paths are read from.
+#[derive(Debug, Clone, Copy)]
+pub enum ValueBase<'a> {
+    Dir(&'a Path),
+    Url(&'a str),
+}
+/// Which remote values may be fetched. The default allows none.
+#[derive(Debug, Clone, Default)]
+pub struct RemotePolicy {
+    schemes: Vec<String>,
+    hosts: Vec<String>,
+    local: bool,
+}
+impl RemotePolicy {
+    /// Allows remote values with a digest whose url uses one of `schemes` and names one of
+    /// `hosts`, for sources that aren't read from disk.
+    pub fn new(
+        schemes: impl IntoIterator<Item = impl Into<String>>,
+        hosts: impl IntoIterator<Item = impl Into<String>>,
+    ) -> Self {
+        Self {
+            schemes: schemes.into_iter().map(Into::into).collect(),
+            hosts: hosts.into_iter().map(Into::into).collect(),
+            local: false,
+        }
+    }
+    /// Also lets manifests read from disk fetch remote values.
+    pub fn with_local(mut self, local: bool) -> Self {
+        self.local = local;
+        self
+    }
+    pub fn check(&self, remote: &RemoteValue, local: bool) -> Result<(), Error> {
+        let denied = |reason| Err(Error::RemoteDenied(remote.url().to_string(), reason));
+        if remote.sha256().is_none() {
+            return denied("it has no sha256 digest");
+        }
+        if local && !self.local {
+            return denied("sources on disk can't fetch remote values");
+        }
+        if !remote
+            .url()
+            .scheme_str()
+            .is_some_and(|scheme| self.schemes.iter().any(|allowed| allowed == scheme))
+        {
+            return denied("its scheme isn't allowed");
+        }
+        if !remote.url().host().is_some_and(|host| {
+            self.hosts
+                .iter()
+                .any(|allowed| allowed.eq_ignore_ascii_case(host))
+        }) {
+            return denied("its host isn't allowed");
+        }
+        Ok(())
+    }
+}
+/// Fetches item values for any [`ValueAccessor`].
+#[derive(Debug, Clone, Copy)]
+pub struct ValueResolver<'a> {
+    base: ValueBase<'a>,
+    policy: Option<&'a RemotePolicy>,
+    #[cfg(feature = "http")]
+    http: Option<&'a HttpResolver>,
+}
+impl<'a> ValueResolver<'a> {
+    pub fn new(base: ValueBase<'a>) -> Self {
+        Self {
+            base,
+            policy: None,
+            #[cfg(feature = "http")]
+            http: None,
+        }
+    }
+    /// Allows remote values as `policy` does, none being fetched without one.
+    pub fn with_policy(mut self, policy: &'a RemotePolicy) -> Self {
+        self.policy = Some(policy);
+        self
+    }
+    #[cfg(feature = "http")]
+    pub fn with_http(mut self, http: &'a HttpResolver) -> Self {
+        self.http = Some(http);
+        self
+    }
+    /// Fetches an item value, checking it against the item digest when there is one.
+    pub async fn fetch_item(&self, item: &Item) -> Result<Vec<u8>, Error> {
+        let value = self.fetch(item.value()).await?;
+        verify(item.name(), item.sha256(), &value)?;
+        Ok(value)
+    }
+    /// Fetches a template file, checking it against the template digest when there is one.
+    pub async fn fetch_template(&self, template: &Template) -> Result<Vec<u8>, Error> {
+        let value = self
+            .fetch(&ValueAccessor::Path(template.source().to_path_buf()))
+            .await?;
+        verify(template.target(), template.sha256(), &value)?;
+        Ok(value)
+    }
+    pub async fn fetch(&self, value: &ValueAccessor) -> Result<Vec<u8>, Error> {
+        match value {
+            ValueAccessor::None => Ok(Vec::new()),
+            ValueAccessor::Path(path) => {
+                if path.is_absolute()
+                    || path
+                        .components()
+                        .any(|component| component == Component::ParentDir)
+                {
+                    return Err(Error::Escapes(path.clone()));
+                }
+                match self.base {
+                    ValueBase::Dir(dir) => read_under(dir, path).await,
+                    ValueBase::Url(location) => self.fetch_relative(location, path).await,
+                }
+            }
+            ValueAccessor::Remote(remote) => {
+                self.policy
+                    .ok_or_else(|| {
+                        Error::RemoteDenied(remote.url().to_string(), "remote values are disabled")
+                    })?
+                    .check(remote, matches!(self.base, ValueBase::Dir(_)))?;
+                let value = self.fetch_remote(remote).await?;
+                verify(&remote.url().to_string(), remote.sha256(), &value)?;
+                Ok(value)
+            }
+        }
+    }
+    #[cfg(feature = "http")]
+    async fn fetch_relative(&self, location: &str, path: &Path) -> Result<Vec<u8>, Error> {
+        let http = self
+            .http
+            .ok_or_else(|| Error::RemoteUnavailable(location.to_string()))?;
+        Ok(http.item(location, path).await?)
+    }
+    #[cfg(not(feature = "http"))]
+    async fn fetch_relative(&self, location: &str, _: &Path) -> Result<Vec<u8>, Error> {
+        Err(Error::RemoteUnavailable(location.to_string()))
+    }
+    #[cfg(feature = "http")]
+    async fn fetch_remote(&self, remote: &RemoteValue) -> Result<Vec<u8>, Error> {
+        let url = remote.url().to_string();
+        let http = self.http.ok_or(Error::RemoteUnavailable(url.clone()))?;
+        Ok(http.get(&url).await?)
+    }
+    #[cfg(not(feature = "http"))]
+    async fn fetch_remote(&self, remote: &RemoteValue) -> Result<Vec<u8>, Error> {
+        Err(Error::RemoteUnavailable(remote.url().to_string()))
+    }
+}
+/// Reads `path` within `root`, checking the file it opened is still under the root so a symlink
+/// swapped in after the manifest was validated can't point it elsewhere.
+async fn read_under(root: &Path, path: &Path) -> Result<Vec<u8>, Error> {
+    let mut file = tokio::fs::File::open(root.join(path)).await?;
+    let opened = tokio::fs::read_link(format!("/proc/self/fd/{}", file.as_raw_fd())).await?;
+    if !opened.starts_with(tokio::fs::canonicalize(root).await?) {
+        return Err(Error::Escapes(path.to_path_buf()));
+    }
+    let mut value = Vec::new();
+    file.read_to_end(&mut value).await?;
+    Ok(value)
+}
+/// Hex encoded SHA-256 digest, as stored in manifests.
+pub fn sha256(value: &[u8]) -> String {
+    hex::encode(Sha256::digest(value))
+}
+fn verify(name: &str, expected: Option<&str>, value: &[u8]) -> Result<(), Error> {
+    let Some(expected) = expected else {
+        return Ok(());
+    };
+    let actual = sha256(value);
+    if actual.eq_ignore_ascii_case(expected) {
+        Ok(())
+    } else {
+        Err(Error::Digest {
+            name: name.to_string(),
+            expected: expected.to_string(),
+            actual,
+        })
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn remote(url: &str, sha256: Option<&str>) -> RemoteValue {
+        RemoteValue::new(url.parse().unwrap(), sha256.map(str::to_string))
+    }
+    const DIGEST: &str = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+
+    #[test]
+    fn remote_values_need_an_allowed_scheme_and_host() {
+        let policy = RemotePolicy::new(["https"], ["secrets.example.com"]);
+        assert!(
+            policy
+                .check(
+                    &remote("https://secrets.example.com/a", Some(DIGEST)),
+                    false
+                )
+                .is_ok()
+        );
+        assert!(
+            policy
+                .check(&remote("http://secrets.example.com/a", Some(DIGEST)), false)
+                .is_err()
+        );
+        assert!(
+            policy
+                .check(
+                    &remote("https://169.254.169.254/latest", Some(DIGEST)),
+                    false
+                )
+                .is_err()
+        );
+    }
+    #[test]
+    fn remote_values_need_a_digest() {
+        let policy = RemotePolicy::new(["https"], ["secrets.example.com"]);
+        assert!(
+            policy
+                .check(&remote("https://secrets.example.com/a", None), false)
+                .is_err()
+        );
+    }
+    #[test]
+    fn local_sources_need_opt_in() {
+        let value = remote("https://secrets.example.com/a", Some(DIGEST));
+        let policy = RemotePolicy::new(["https"], ["secrets.example.com"]);
+        assert!(policy.check(&value, true).is_err());
+        assert!(policy.with_local(true).check(&value, true).is_ok());
+    }
+    #[tokio::test]
+    async fn paths_stay_under_the_root() {
+        let dir = std::env::temp_dir().join(format!("hairpin-value-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("root/items")).unwrap();
+        std::fs::write(dir.join("root/items/a"), "inside").unwrap();
+        std::fs::write(dir.join("outside"), "outside").unwrap();
+        std::os::unix::fs::symlink(dir.join("outside"), dir.join("root/items/b")).unwrap();
+        std::os::unix::fs::symlink("a", dir.join("root/items/c")).unwrap();
+        let root = dir.join("root");
+        let resolver = ValueResolver::new(ValueBase::Dir(&root));
+        let fetch = |path: &str| {
+            let value = ValueAccessor::Path(PathBuf::from(path));
+            async move { resolver.fetch(&value).await }
+        };
+        assert_eq!(fetch("items/a").await.unwrap(), b"inside");
+        assert_eq!(fetch("items/c").await.unwrap(), b"inside");
+        assert!(matches!(fetch("items/b").await, Err(Error::Escapes(_))));
+        assert!(matches!(fetch("../outside").await, Err(Error::Escapes(_))));
+    }
+    #[tokio::test]
+    async fn remote_values_are_denied_without_a_policy() {
+        let value = ValueAccessor::Remote(remote("https://secrets.example.com/a", Some(DIGEST)));
+        let result = ValueResolver::new(ValueBase::Url("https://secrets.example.com/"))
+            .fetch(&value)
+            .await;
+        assert!(matches!(result, Err(Error::RemoteDenied(_, _))));
+    }
+}
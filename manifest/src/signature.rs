@@ -0,0 +1,62 @@
+use ed25519_dalek::{Signature, Signer, Verifier};
+use rand_core::OsRng;
+
+pub use ed25519_dalek::{SigningKey, VerifyingKey};
+
+use crate::Manifest;
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("Invalid hex encoding: {0}")]
+    Encoding(#[from] hex::FromHexError),
+    #[error("Expected {0} bytes, got {1}")]
+    Length(usize, usize),
+    #[error("Invalid key: {0}")]
+    InvalidKey(#[from] ed25519_dalek::SignatureError),
+    #[error("Unable to serialize manifest: {0}")]
+    Canonical(#[from] toml::ser::Error),
+    #[error("Signature doesn't match any trusted key")]
+    Untrusted,
+}
+pub fn generate() -> SigningKey {
+    SigningKey::generate(&mut OsRng)
+}
+/// Hex encoding used for keys and signature files.
+pub fn encode(bytes: &[u8]) -> String {
+    hex::encode(bytes)
+}
+pub fn parse_signing_key(text: &str) -> Result<SigningKey, Error> {
+    Ok(SigningKey::from_bytes(&decode(text)?))
+}
+pub fn parse_verifying_key(text: &str) -> Result<VerifyingKey, Error> {
+    Ok(VerifyingKey::from_bytes(&decode(text)?)?)
+}
+fn decode<const N: usize>(text: &str) -> Result<[u8; N], Error> {
+    let bytes = hex::decode(text.trim())?;
+    let length = bytes.len();
+    bytes.try_into().map_err(|_| Error::Length(N, length))
+}
+impl Manifest {
+    pub const SIGNATURE_NAME: &'static str = "Hairpin.toml.sig";
+    /// The serialized form signatures cover, independent of the formatting of the document.
+    pub fn canonical(&self) -> Result<String, Error> {
+        Ok(toml::to_string(self)?)
+    }
+    /// Detached hex encoded signature over the canonical manifest.
+    pub fn sign(&self, key: &SigningKey) -> Result<String, Error> {
+        Ok(encode(&key.sign(self.canonical()?.as_bytes()).to_bytes()))
+    }
+    /// Returns the trusted key that made `signature`.
+    pub fn verify<'a>(
+        &self,
+        signature: &str,
+        trusted: &'a [VerifyingKey],
+    ) -> Result<&'a VerifyingKey, Error> {
+        let signature = Signature::from_bytes(&decode(signature)?);
+        let canonical = self.canonical()?;
+        trusted
+            .iter()
+            .find(|key| key.verify(canonical.as_bytes(), &signature).is_ok())
+            .ok_or(Error::Untrusted)
+    }
+}
@@ -0,0 +1,467 @@
+use std::collections::HashMap;
+
+use toml::Value;
+
+use crate::{Item, Manifest};
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("line {line}: {message}")]
+    Syntax { line: usize, message: String },
+    #[error("template isn't valid UTF-8")]
+    Encoding,
+    #[error("no item {0}")]
+    UnknownItem(String),
+    #[error("no item is labeled {0}")]
+    UnknownLabel(String),
+    #[error("{1} items are labeled {0}, expected one")]
+    AmbiguousLabel(String, usize),
+    #[error("item {0} has no property {1}")]
+    UnknownProperty(String, String),
+    #[error("property {1} of item {0} isn't a string, number, boolean or date")]
+    UnsupportedProperty(String, String),
+    #[error("value of item {0} is unavailable")]
+    Unavailable(String),
+    #[error("{0} isn't valid UTF-8 and can't be JSON escaped")]
+    NotText(String),
+    #[error("{0} contains a NUL byte and can't be shell quoted")]
+    NotShellSafe(String),
+}
+/// How an expression refers to an item.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ItemRef {
+    /// An item id or name.
+    Key(String),
+    /// The one item carrying a label.
+    Label(String),
+}
+impl ItemRef {
+    pub fn find<'a>(&self, manifest: &'a Manifest) -> Result<&'a Item, Error> {
+        let items = manifest.items();
+        match self {
+            ItemRef::Key(key) => items
+                .iter()
+                .find(|item| item.id() == key)
+                .or_else(|| items.iter().find(|item| item.name() == key))
+                .ok_or_else(|| Error::UnknownItem(key.clone())),
+            ItemRef::Label(label) => {
+                let labeled = items
+                    .iter()
+                    .filter(|item| item.labels().contains(label))
+                    .collect::<Vec<_>>();
+                match labeled.as_slice() {
+                    [item] => Ok(item),
+                    [] => Err(Error::UnknownLabel(label.clone())),
+                    _ => Err(Error::AmbiguousLabel(label.clone(), labeled.len())),
+                }
+            }
+        }
+    }
+}
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Input {
+    Value(ItemRef),
+    Property(ItemRef, String),
+    Literal(String),
+}
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Filter {
+    Trim,
+    Shell,
+    Json,
+    Url,
+}
+impl Filter {
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "trim" => Some(Filter::Trim),
+            "shell" => Some(Filter::Shell),
+            "json" => Some(Filter::Json),
+            "url" => Some(Filter::Url),
+            _ => None,
+        }
+    }
+    fn apply(&self, value: Vec<u8>, input: &str) -> Result<Vec<u8>, Error> {
+        match self {
+            Filter::Trim => Ok(value.trim_ascii().to_vec()),
+            Filter::Shell => {
+                if value.contains(&0) {
+                    return Err(Error::NotShellSafe(input.into()));
+                }
+                let mut output = vec![b'\''];
+                for byte in value {
+                    match byte {
+                        b'\'' => output.extend_from_slice(b"'\\''"),
+                        byte => output.push(byte),
+                    }
+                }
+                output.push(b'\'');
+                Ok(output)
+            }
+            Filter::Json => {
+                let text = String::from_utf8(value).map_err(|_| Error::NotText(input.into()))?;
+                let mut output = String::from("\"");
+                for char in text.chars() {
+                    match char {
+                        '"' => output.push_str("\\\""),
+                        '\\' => output.push_str("\\\\"),
+                        '\n' => output.push_str("\\n"),
+                        '\r' => output.push_str("\\r"),
+                        '\t' => output.push_str("\\t"),
+                        char if char.is_control() => {
+                            output.push_str(&format!("\\u{:04x}", char as u32))
+                        }
+                        char => output.push(char),
+                    }
+                }
+                output.push('"');
+                Ok(output.into_bytes())
+            }
+            Filter::Url => {
+                let mut output = Vec::with_capacity(value.len());
+                for byte in value {
+                    match byte {
+                        b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                            output.push(byte)
+                        }
+                        byte => output.extend_from_slice(format!("%{byte:02X}").as_bytes()),
+                    }
+                }
+                Ok(output)
+            }
+        }
+    }
+}
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Expression {
+    input: Input,
+    filters: Vec<Filter>,
+}
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Part {
+    Text(String),
+    Expression(Expression),
+}
+/// A parsed template. Text is copied as is and `{{ ... }}` expressions are replaced:
+///
+/// - `{{ value "name" }}` inserts the value of the item with that id or name,
+/// - `{{ value label "db" }}` that of the one item labeled `db`,
+/// - `{{ property "name" "port" }}` a property of an item, also selectable by label,
+/// - `{{ "{{" }}` a literal string.
+///
+/// Each may be piped through `trim`, `shell`, `json` or `url`, as in
+/// `{{ value "pw" | trim | url }}`.
+/// Referring to anything that doesn't exist fails the whole template.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TemplateDocument {
+    parts: Vec<Part>,
+}
+impl TemplateDocument {
+    pub fn parse(text: &str) -> Result<Self, Error> {
+        let mut parts = Vec::new();
+        let mut rest = text;
+        while let Some(start) = rest.find("{{") {
+            let line = text[..text.len() - rest.len() + start]
+                .matches('\n')
+                .count()
+                + 1;
+            if start > 0 {
+                parts.push(Part::Text(rest[..start].to_string()));
+            }
+            let body = &rest[start + 2..];
+            let end = find_end(body).ok_or_else(|| Error::Syntax {
+                line,
+                message: "unclosed {{".to_string(),
+            })?;
+            let expression = parse_expression(&body[..end])
+                .map_err(|message| Error::Syntax { line, message })?;
+            parts.push(Part::Expression(expression));
+            rest = &body[end + 2..];
+        }
+        if !rest.is_empty() {
+            parts.push(Part::Text(rest.to_string()));
+        }
+        Ok(Self { parts })
+    }
+    /// Items whose values the template inserts.
+    pub fn items<'a>(&self, manifest: &'a Manifest) -> Result<Vec<&'a Item>, Error> {
+        let mut items = Vec::<&Item>::new();
+        for part in &self.parts {
+            if let Part::Expression(Expression {
+                input: Input::Value(reference),
+                ..
+            }) = part
+            {
+                let item = reference.find(manifest)?;
+                if !items.iter().any(|value| value.id() == item.id()) {
+                    items.push(item);
+                }
+            }
+        }
+        Ok(items)
+    }
+    /// Checks that everything the template refers to exists in `manifest`.
+    pub fn check(&self, manifest: &Manifest) -> Result<(), Error> {
+        for part in &self.parts {
+            if let Part::Expression(expression) = part {
+                match &expression.input {
+                    Input::Value(reference) => {
+                        reference.find(manifest)?;
+                    }
+                    Input::Property(reference, name) => {
+                        property(reference.find(manifest)?, name)?;
+                    }
+                    Input::Literal(_) => {}
+                }
+            }
+        }
+        Ok(())
+    }
+    /// Renders the template with item values by item id.
+    pub fn render(
+        &self,
+        manifest: &Manifest,
+        values: &HashMap<String, Vec<u8>>,
+    ) -> Result<Vec<u8>, Error> {
+        let mut output = Vec::new();
+        for part in &self.parts {
+            match part {
+                Part::Text(text) => output.extend_from_slice(text.as_bytes()),
+                Part::Expression(expression) => {
+                    let (mut value, input) = match &expression.input {
+                        Input::Value(reference) => {
+                            let item = reference.find(manifest)?;
+                            let value = values
+                                .get(item.id())
+                                .ok_or_else(|| Error::Unavailable(item.name().to_string()))?;
+                            (value.clone(), format!("value of item {}", item.name()))
+                        }
+                        Input::Property(reference, name) => {
+                            let item = reference.find(manifest)?;
+                            (
+                                property(item, name)?.into_bytes(),
+                                format!("property {name} of item {}", item.name()),
+                            )
+                        }
+                        Input::Literal(text) => (text.clone().into_bytes(), "literal".to_string()),
+                    };
+                    for filter in &expression.filters {
+                        value = filter.apply(value, &input)?;
+                    }
+                    output.extend_from_slice(&value);
+                }
+            }
+        }
+        Ok(output)
+    }
+}
+fn property(item: &Item, name: &str) -> Result<String, Error> {
+    match item.properties().get(name) {
+        Some(Value::String(value)) => Ok(value.clone()),
+        Some(Value::Integer(value)) => Ok(value.to_string()),
+        Some(Value::Float(value)) => Ok(value.to_string()),
+        Some(Value::Boolean(value)) => Ok(value.to_string()),
+        Some(Value::Datetime(value)) => Ok(value.to_string()),
+        Some(_) => Err(Error::UnsupportedProperty(
+            item.name().to_string(),
+            name.to_string(),
+        )),
+        None => Err(Error::UnknownProperty(
+            item.name().to_string(),
+            name.to_string(),
+        )),
+    }
+}
+/// Offset of the `}}` closing an expression, skipping over string literals.
+fn find_end(body: &str) -> Option<usize> {
+    let mut quoted = false;
+    let mut escaped = false;
+    for (index, char) in body.char_indices() {
+        match char {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            '}' if !quoted && body[index..].starts_with("}}") => return Some(index),
+            _ => {}
+        }
+    }
+    None
+}
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Word(String),
+    String(String),
+    Pipe,
+}
+fn tokenize(text: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = text.chars().peekable();
+    while let Some(char) = chars.next() {
+        match char {
+            char if char.is_whitespace() => {}
+            '|' => tokens.push(Token::Pipe),
+            '"' => {
+                let mut value = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some('n') => value.push('\n'),
+                            Some('t') => value.push('\t'),
+                            Some(char @ ('"' | '\\')) => value.push(char),
+                            Some(char) => return Err(format!("unknown escape \\{char}")),
+                            None => return Err("unterminated string".to_string()),
+                        },
+                        Some(char) => value.push(char),
+                        None => return Err("unterminated string".to_string()),
+                    }
+                }
+                tokens.push(Token::String(value));
+            }
+            char if char.is_ascii_alphabetic() => {
+                let mut word = String::from(char);
+                while let Some(char) = chars.next_if(|char| char.is_ascii_alphanumeric()) {
+                    word.push(char);
+                }
+                tokens.push(Token::Word(word));
+            }
+            char => return Err(format!("unexpected {char:?}")),
+        }
+    }
+    Ok(tokens)
+}
+fn parse_expression(text: &str) -> Result<Expression, String> {
+    let mut tokens = tokenize(text)?.into_iter();
+    let reference = |tokens: &mut std::vec::IntoIter<Token>| match tokens.next() {
+        Some(Token::String(key)) => Ok(ItemRef::Key(key)),
+        Some(Token::Word(word)) if word == "label" => match tokens.next() {
+            Some(Token::String(label)) => Ok(ItemRef::Label(label)),
+            _ => Err("expected a quoted label after label".to_string()),
+        },
+        _ => Err("expected a quoted item id or name, or label".to_string()),
+    };
+    let input = match tokens.next() {
+        Some(Token::Word(word)) if word == "value" => Input::Value(reference(&mut tokens)?),
+        Some(Token::Word(word)) if word == "property" => {
+            let item = reference(&mut tokens)?;
+            match tokens.next() {
+                Some(Token::String(name)) => Input::Property(item, name),
+                _ => return Err("expected a quoted property name".to_string()),
+            }
+        }
+        Some(Token::String(text)) => Input::Literal(text),
+        Some(Token::Word(word)) => return Err(format!("unknown function {word}")),
+        _ => return Err("expected value, property or a quoted string".to_string()),
+    };
+    let mut filters = Vec::new();
+    while let Some(token) = tokens.next() {
+        if token != Token::Pipe {
+            return Err("expected | between filters".to_string());
+        }
+        match tokens.next() {
+            Some(Token::Word(name)) => {
+                filters.push(Filter::parse(&name).ok_or_else(|| format!("unknown filter {name}"))?)
+            }
+            _ => return Err("expected a filter after |".to_string()),
+        }
+    }
+    Ok(Expression { input, filters })
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manifest() -> Manifest {
+        toml::from_str(
+            r#"
+            id = "source"
+            name = "source"
+            version = "0.1.0"
+            labels = []
+            [[items]]
+            id = "password"
+            name = "password"
+            encryption = "none"
+            labels = ["db"]
+            [items.properties]
+            port = 5432
+            [properties]
+            "#,
+        )
+        .unwrap()
+    }
+    fn render(template: &str, value: &[u8]) -> Result<String, Error> {
+        let values = HashMap::from([("password".to_string(), value.to_vec())]);
+        let rendered = TemplateDocument::parse(template)?.render(&manifest(), &values)?;
+        Ok(String::from_utf8(rendered).unwrap())
+    }
+    #[test]
+    fn inserts_values_and_properties() {
+        let rendered = render(
+            r#"{{ value "password" }}:{{ property label "db" "port" }} {{ "{{" }}"#,
+            b"secret",
+        );
+        assert_eq!(rendered.unwrap(), "secret:5432 {{");
+    }
+    #[test]
+    fn shell_quotes_values() {
+        let shell = |value: &[u8]| render(r#"{{ value "password" | shell }}"#, value);
+        assert_eq!(shell(b"a b").unwrap(), "'a b'");
+        assert_eq!(shell(b"it's").unwrap(), r"'it'\''s'");
+        assert_eq!(shell(b"$(rm -rf /)\n`x`").unwrap(), "'$(rm -rf /)\n`x`'");
+        assert!(matches!(shell(b"a\0b"), Err(Error::NotShellSafe(_))));
+    }
+    #[test]
+    fn json_escapes_values() {
+        let json = |value: &[u8]| render(r#"{{ value "password" | json }}"#, value);
+        assert_eq!(json(br#"say "hi" \o/"#).unwrap(), r#""say \"hi\" \\o/""#);
+        assert_eq!(
+            json(b"a\nb\tc\x01\x7f").unwrap(),
+            r#""a\nb\tc\u0001\u007f""#
+        );
+        assert!(matches!(json(b"\xff"), Err(Error::NotText(_))));
+    }
+    #[test]
+    fn url_encodes_values() {
+        let url = |value: &[u8]| render(r#"{{ value "password" | trim | url }}"#, value);
+        assert_eq!(
+            url(b" a b&c=d/\"e\"~\n").unwrap(),
+            "a%20b%26c%3Dd%2F%22e%22~"
+        );
+        assert_eq!(url(b"\xff\x00").unwrap(), "%FF%00");
+    }
+    #[test]
+    fn braces_in_strings_dont_close_expressions() {
+        assert_eq!(render(r#"{{ "}} \" \\" }}"#, b"").unwrap(), r#"}} " \"#);
+    }
+    #[test]
+    fn rejects_malformed_expressions() {
+        let line = |template: &str| match TemplateDocument::parse(template) {
+            Err(Error::Syntax { line, .. }) => line,
+            result => panic!("expected a syntax error, got {result:?}"),
+        };
+        assert_eq!(line("a\nb {{ value \"password\""), 2);
+        assert_eq!(line("{{ \"unterminated }}"), 1);
+        assert_eq!(line("{{ value \"password\" | base64 }}"), 1);
+        assert_eq!(line("{{ value \"password\" trim }}"), 1);
+        assert_eq!(line("{{ \"\\x\" }}"), 1);
+        assert_eq!(line("{{ run \"password\" }}"), 1);
+    }
+    #[test]
+    fn unknown_references_fail() {
+        let manifest = manifest();
+        let check = |template| TemplateDocument::parse(template).unwrap().check(&manifest);
+        assert!(matches!(
+            check(r#"{{ value "other" }}"#),
+            Err(Error::UnknownItem(_))
+        ));
+        assert!(matches!(
+            check(r#"{{ value label "web" }}"#),
+            Err(Error::UnknownLabel(_))
+        ));
+        assert!(matches!(
+            check(r#"{{ property "password" "host" }}"#),
+            Err(Error::UnknownProperty(_, _))
+        ));
+    }
+}
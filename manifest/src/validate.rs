@@ -0,0 +1,393 @@
+use std::{
+    collections::{HashMap, hash_map::Entry},
+    fmt::Display,
+    ops::Range,
+    path::{Component, Path},
+};
+
+use serde::Deserialize;
+use toml::{Spanned, Value};
+
+use crate::{ItemEncryption, KdfParameters, Manifest, RemoteValue};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+impl Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Severity::Warning => f.write_str("warning"),
+            Severity::Error => f.write_str("error"),
+        }
+    }
+}
+/// A validation finding, with the byte range it refers to in the manifest document.
+#[derive(Clone, Debug)]
+pub struct Diagnostic {
+    severity: Severity,
+    span: Option<Range<usize>>,
+    message: String,
+}
+impl Diagnostic {
+    pub fn error(span: Option<Range<usize>>, message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Error,
+            span,
+            message: message.into(),
+        }
+    }
+    pub fn warning(span: Option<Range<usize>>, message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Warning,
+            span,
+            message: message.into(),
+        }
+    }
+    pub fn severity(&self) -> Severity {
+        self.severity
+    }
+    pub fn span(&self) -> Option<&Range<usize>> {
+        self.span.as_ref()
+    }
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+    /// One-based line and column of the start of the span within `document`.
+    pub fn location(&self, document: &str) -> Option<(usize, usize)> {
+        let start = self.span.as_ref()?.start.min(document.len());
+        let before = &document[..start];
+        let column = before.rfind('\n').map_or(before.chars().count(), |index| {
+            before[index + 1..].chars().count()
+        }) + 1;
+        Some((line(document, start), column))
+    }
+}
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.severity, self.message)
+    }
+}
+#[derive(Deserialize)]
+struct RawManifest {
+    version: Option<Spanned<Value>>,
+    #[serde(default)]
+    items: Vec<Spanned<RawItem>>,
+    #[serde(default)]
+    templates: Vec<Spanned<RawTemplate>>,
+}
+#[derive(Deserialize)]
+struct RawTemplate {
+    source: Option<Spanned<Value>>,
+    target: Option<Spanned<Value>>,
+    sha256: Option<Spanned<Value>>,
+}
+#[derive(Deserialize)]
+struct RawItem {
+    id: Option<Spanned<Value>>,
+    name: Option<Spanned<Value>>,
+    value: Option<Spanned<Value>>,
+    sha256: Option<Spanned<Value>>,
+    // Implicit tables such as `[items.encryption.passphrase]` carry no span.
+    encryption: Option<Value>,
+}
+impl Manifest {
+    /// Checks a manifest document whose item paths are relative to `root`.
+    /// Without a root, such as for remote manifests, value files aren't checked on disk.
+    pub fn validate(document: &str, root: Option<&Path>) -> Vec<Diagnostic> {
+        let raw = match toml::from_str::<RawManifest>(document) {
+            Ok(raw) => raw,
+            Err(err) => return vec![Diagnostic::error(err.span(), err.message())],
+        };
+        let mut diagnostics = Vec::new();
+        validate_version(raw.version.as_ref(), &mut diagnostics);
+        validate_unique(
+            document,
+            &raw.items,
+            |item| item.id.as_ref(),
+            "id",
+            &mut diagnostics,
+        );
+        validate_unique(
+            document,
+            &raw.items,
+            |item| item.name.as_ref(),
+            "name",
+            &mut diagnostics,
+        );
+        let canonical_root = root.map(|root| (root, root.canonicalize()));
+        for spanned in &raw.items {
+            let item = spanned.get_ref();
+            if item.value.is_none() {
+                diagnostics.push(Diagnostic::warning(
+                    Some(spanned.span()),
+                    "item has no value",
+                ));
+            }
+            if let Some(encryption) = &item.encryption {
+                match ItemEncryption::deserialize(encryption.clone()) {
+                    Err(err) => diagnostics.push(Diagnostic::error(
+                        Some(spanned.span()),
+                        format!("unknown encryption scheme {encryption}: {}", err.message()),
+                    )),
+                    Ok(ItemEncryption::Passphrase(parameters)) if !parameters.is_bounded() => {
+                        diagnostics.push(Diagnostic::error(
+                            Some(spanned.span()),
+                            format!(
+                                "scrypt parameters must not exceed log-n {}, r {} and p {}",
+                                KdfParameters::MAX_LOG_N,
+                                KdfParameters::MAX_R,
+                                KdfParameters::MAX_P
+                            ),
+                        ))
+                    }
+                    Ok(_) => {}
+                }
+            }
+            if let Some(value) = &item.value
+                && let Some(path) = value.get_ref().as_str()
+                && let Err(message) =
+                    validate_path("value", Path::new(path), canonical_root.as_ref())
+            {
+                diagnostics.push(Diagnostic::error(Some(value.span()), message));
+            }
+            if let Some(value) = &item.value
+                && value.get_ref().is_table()
+                && let Err(message) = validate_remote(value.get_ref())
+            {
+                diagnostics.push(Diagnostic::error(Some(value.span()), message));
+            }
+            if let Some(sha256) = &item.sha256
+                && let Some(digest) = sha256.get_ref().as_str()
+                && let Err(message) = validate_digest(digest)
+            {
+                diagnostics.push(Diagnostic::error(Some(sha256.span()), message));
+            }
+        }
+        validate_templates(&raw, canonical_root.as_ref(), &mut diagnostics);
+        if diagnostics.is_empty() {
+            match toml::from_str::<Manifest>(document) {
+                Ok(manifest) => {
+                    validate_template_references(&raw, &manifest, root, &mut diagnostics)
+                }
+                Err(err) => diagnostics.push(Diagnostic::error(err.span(), err.message())),
+            }
+        }
+        diagnostics
+    }
+}
+fn validate_templates(
+    raw: &RawManifest,
+    root: Option<&(&Path, std::io::Result<std::path::PathBuf>)>,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    let names = raw
+        .items
+        .iter()
+        .filter_map(|item| item.get_ref().name.as_ref()?.get_ref().as_str())
+        .collect::<Vec<_>>();
+    let mut targets = Vec::new();
+    for spanned in &raw.templates {
+        let template = spanned.get_ref();
+        match &template.source {
+            Some(source) => {
+                if let Some(path) = source.get_ref().as_str()
+                    && let Err(message) = validate_path("template", Path::new(path), root)
+                {
+                    diagnostics.push(Diagnostic::error(Some(source.span()), message));
+                }
+            }
+            None => diagnostics.push(Diagnostic::error(
+                Some(spanned.span()),
+                "template has no source",
+            )),
+        }
+        if let Some(sha256) = &template.sha256
+            && let Some(digest) = sha256.get_ref().as_str()
+            && let Err(message) = validate_digest(digest)
+        {
+            diagnostics.push(Diagnostic::error(Some(sha256.span()), message));
+        }
+        let Some(target) = &template.target else {
+            diagnostics.push(Diagnostic::error(
+                Some(spanned.span()),
+                "template has no target",
+            ));
+            continue;
+        };
+        let Some(name) = target.get_ref().as_str() else {
+            continue;
+        };
+        let mut components = Path::new(name).components();
+        if !matches!(
+            (components.next(), components.next()),
+            (Some(Component::Normal(_)), None)
+        ) || name.starts_with('.')
+        {
+            diagnostics.push(Diagnostic::error(
+                Some(target.span()),
+                format!("template target {name:?} must be a plain file name"),
+            ));
+        } else if names.contains(&name) {
+            diagnostics.push(Diagnostic::error(
+                Some(target.span()),
+                format!("template target {name:?} is also an item name"),
+            ));
+        } else if targets.contains(&name) {
+            diagnostics.push(Diagnostic::error(
+                Some(target.span()),
+                format!("duplicate template target {name:?}"),
+            ));
+        }
+        targets.push(name);
+    }
+}
+/// Parses each template file and checks the items and properties it refers to.
+#[cfg(feature = "template")]
+fn validate_template_references(
+    raw: &RawManifest,
+    manifest: &Manifest,
+    root: Option<&Path>,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    let Some(root) = root else {
+        return;
+    };
+    for (spanned, template) in raw.templates.iter().zip(manifest.templates()) {
+        let span = spanned
+            .get_ref()
+            .source
+            .as_ref()
+            .map_or(spanned.span(), |source| source.span());
+        let result = std::fs::read(root.join(template.source()))
+            .map_err(|err| err.to_string())
+            .and_then(|text| String::from_utf8(text).map_err(|err| err.to_string()))
+            .and_then(|text| {
+                crate::template::TemplateDocument::parse(&text)
+                    .and_then(|document| document.check(manifest))
+                    .map_err(|err| err.to_string())
+            });
+        if let Err(message) = result {
+            diagnostics.push(Diagnostic::error(
+                Some(span),
+                format!("template {}: {message}", template.source().display()),
+            ));
+        }
+    }
+}
+fn validate_version(version: Option<&Spanned<Value>>, diagnostics: &mut Vec<Diagnostic>) {
+    let Some(version) = version else {
+        return;
+    };
+    if let Some(value) = version.get_ref().as_str()
+        && let Err(err) = semver::Version::parse(value)
+    {
+        diagnostics.push(Diagnostic::error(
+            Some(version.span()),
+            format!("malformed version {value:?}: {err}"),
+        ));
+    }
+}
+fn validate_remote(value: &Value) -> Result<(), String> {
+    let remote = RemoteValue::deserialize(value.clone())
+        .map_err(|err| format!("invalid remote value: {}", err.message()))?;
+    if !matches!(remote.url().scheme_str(), Some("http" | "https")) {
+        return Err(format!(
+            "remote value {} must use http or https",
+            remote.url()
+        ));
+    }
+    match remote.sha256() {
+        Some(sha256) => validate_digest(sha256),
+        None => Err(format!(
+            "remote value {} has no sha256 digest",
+            remote.url()
+        )),
+    }
+}
+fn validate_digest(sha256: &str) -> Result<(), String> {
+    if sha256.len() != 64 || !sha256.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!(
+            "sha256 digest {sha256:?} must be 64 hex characters"
+        ));
+    }
+    Ok(())
+}
+#[cfg(not(feature = "template"))]
+fn validate_template_references(
+    _: &RawManifest,
+    _: &Manifest,
+    _: Option<&Path>,
+    _: &mut Vec<Diagnostic>,
+) {
+}
+fn line(document: &str, offset: usize) -> usize {
+    document[..offset.min(document.len())].matches('\n').count() + 1
+}
+fn validate_unique(
+    document: &str,
+    items: &[Spanned<RawItem>],
+    key: fn(&RawItem) -> Option<&Spanned<Value>>,
+    field: &str,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    let mut seen = HashMap::new();
+    for value in items.iter().filter_map(|item| key(item.get_ref())) {
+        let Some(text) = value.get_ref().as_str() else {
+            continue;
+        };
+        match seen.entry(text) {
+            Entry::Occupied(first) => diagnostics.push(Diagnostic::error(
+                Some(value.span()),
+                format!(
+                    "duplicate item {field} {text:?}, first defined on line {}",
+                    line(document, *first.get())
+                ),
+            )),
+            Entry::Vacant(entry) => {
+                entry.insert(value.span().start);
+            }
+        }
+    }
+}
+fn validate_path(
+    kind: &str,
+    path: &Path,
+    root: Option<&(&Path, std::io::Result<std::path::PathBuf>)>,
+) -> Result<(), String> {
+    if path.is_absolute() {
+        return Err(format!("{kind} path {} is absolute", path.display()));
+    }
+    if path
+        .components()
+        .any(|component| component == Component::ParentDir)
+    {
+        return Err(format!(
+            "{kind} path {} escapes the source root",
+            path.display()
+        ));
+    }
+    let Some((root, canonical_root)) = root else {
+        return Ok(());
+    };
+    let full = root.join(path);
+    if let Err(err) = full.symlink_metadata() {
+        return Err(format!("{kind} file {} is missing: {err}", path.display()));
+    }
+    let resolved = full
+        .canonicalize()
+        .map_err(|err| format!("{kind} file {} can't be resolved: {err}", path.display()))?;
+    match canonical_root {
+        Ok(root) if !resolved.starts_with(root) => Err(format!(
+            "{kind} file {} resolves to {} outside the source root",
+            path.display(),
+            resolved.display()
+        )),
+        Ok(_) => Ok(()),
+        Err(err) => Err(format!("source root can't be resolved: {err}")),
+    }
+}